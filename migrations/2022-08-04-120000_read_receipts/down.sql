@@ -0,0 +1 @@
+-- Read states set by up.sql cannot be told apart from real ones, nothing to undo
\ No newline at end of file
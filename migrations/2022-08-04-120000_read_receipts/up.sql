@@ -0,0 +1 @@
+UPDATE messages SET "read" = TRUE WHERE receiver_user IS NOT NULL AND "read" IS NULL;
\ No newline at end of file
@@ -0,0 +1 @@
+-- The placeholder passwords removed by up.sql were never usable, nothing to restore
\ No newline at end of file
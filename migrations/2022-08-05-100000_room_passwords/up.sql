@@ -0,0 +1 @@
+UPDATE rooms SET "password" = NULL WHERE "password" = 'To do';
\ No newline at end of file
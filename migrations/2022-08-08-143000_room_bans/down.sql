@@ -0,0 +1 @@
+DROP TABLE room_bans;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE rooms ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE game_rounds;
+DROP TABLE game_players;
+DROP TABLE games;
\ No newline at end of file
@@ -0,0 +1,37 @@
+CREATE TABLE games (
+    id VARCHAR (36) PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    host VARCHAR (36) NOT NULL,
+    gg_score INT NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'running',
+    winner VARCHAR (36),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ,
+    FOREIGN KEY (host) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (winner) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE game_players (
+    game_id VARCHAR (36) NOT NULL,
+    user_id VARCHAR (36) NOT NULL,
+    score INT NOT NULL DEFAULT 0,
+    forfeited BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT UQ_game_player_pair PRIMARY KEY (game_id, user_id)
+);
+
+CREATE TABLE game_rounds (
+    game_id VARCHAR (36) NOT NULL,
+    round INT NOT NULL,
+    choices JSONB NOT NULL,
+    excluded JSONB NOT NULL,
+    eliminated JSONB NOT NULL,
+    winner VARCHAR (36),
+    played_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (winner) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT UQ_game_round PRIMARY KEY (game_id, round)
+);
+
+CREATE INDEX game_players_user_idx ON game_players (user_id);
\ No newline at end of file
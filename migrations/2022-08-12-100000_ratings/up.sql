@@ -0,0 +1,29 @@
+CREATE TABLE ratings (
+    user_id VARCHAR (36) PRIMARY KEY,
+    rating DOUBLE PRECISION NOT NULL DEFAULT 1200,
+    wins INT NOT NULL DEFAULT 0,
+    games_played INT NOT NULL DEFAULT 0,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE rating_history (
+    id SERIAL PRIMARY KEY,
+    user_id VARCHAR (36) NOT NULL,
+    game_id VARCHAR (36) NOT NULL,
+    rating_before DOUBLE PRECISION NOT NULL,
+    rating_after DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE
+);
+
+CREATE INDEX rating_history_user_idx ON rating_history (user_id, created_at);
+
+-- Seed the records from the games played so far, the hall of fame only counts wins
+INSERT INTO ratings (user_id, wins, games_played)
+SELECT game_players.user_id,
+       COUNT(*) FILTER (WHERE games.winner = game_players.user_id),
+       COUNT(*)
+FROM game_players
+INNER JOIN games ON games.id = game_players.game_id
+GROUP BY game_players.user_id;
@@ -0,0 +1,3 @@
+-- Admins can roll seasons over. Nothing in the API grants it, an operator does with
+-- UPDATE users SET is_admin = TRUE WHERE username = '<username>';
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+ALTER TABLE games DROP COLUMN season_id;
+DROP TABLE season_standings;
+DROP TABLE seasons;
\ No newline at end of file
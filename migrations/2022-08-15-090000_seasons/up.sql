@@ -0,0 +1,22 @@
+CREATE TABLE seasons (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    starts_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ends_at TIMESTAMPTZ
+);
+
+CREATE TABLE season_standings (
+    season_id INT NOT NULL,
+    user_id VARCHAR (36) NOT NULL,
+    rank INT NOT NULL,
+    wins INT NOT NULL,
+    games_played INT NOT NULL,
+    FOREIGN KEY (season_id) REFERENCES seasons(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT UQ_season_standing_pair PRIMARY KEY (season_id, user_id)
+);
+
+ALTER TABLE games ADD COLUMN season_id INT REFERENCES seasons(id) ON DELETE SET NULL;
+
+INSERT INTO seasons (name) VALUES ('Season 1');
+UPDATE games SET season_id = (SELECT id FROM seasons LIMIT 1);
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE games DROP COLUMN tournament_id;
+DROP TABLE tournament_players;
+DROP TABLE tournaments;
\ No newline at end of file
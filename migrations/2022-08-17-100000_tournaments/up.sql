@@ -0,0 +1,27 @@
+CREATE TABLE tournaments (
+    id VARCHAR (36) PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    host VARCHAR (36) NOT NULL,
+    format VARCHAR NOT NULL,
+    best_of INT NOT NULL,
+    status VARCHAR NOT NULL,
+    champion VARCHAR (36),
+    bracket JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ,
+    FOREIGN KEY (host) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (champion) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE tournament_players (
+    tournament_id VARCHAR (36) NOT NULL,
+    user_id VARCHAR (36) NOT NULL,
+    seed INT NOT NULL,
+    wins INT NOT NULL,
+    losses INT NOT NULL,
+    FOREIGN KEY (tournament_id) REFERENCES tournaments(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT UQ_tournament_player_pair PRIMARY KEY (tournament_id, user_id)
+);
+
+ALTER TABLE games ADD COLUMN tournament_id VARCHAR (36) REFERENCES tournaments(id) ON DELETE SET NULL;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE game_rounds DROP COLUMN commitments;
\ No newline at end of file
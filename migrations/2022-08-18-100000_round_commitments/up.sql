@@ -0,0 +1 @@
+ALTER TABLE game_rounds ADD COLUMN commitments JSONB NOT NULL DEFAULT '{}';
\ No newline at end of file
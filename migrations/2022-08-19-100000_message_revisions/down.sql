@@ -0,0 +1,4 @@
+ALTER TABLE messages DROP COLUMN deleted_by;
+ALTER TABLE messages DROP COLUMN deleted_at;
+ALTER TABLE messages DROP COLUMN edited_at;
+DROP TABLE message_revisions;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE message_revisions (
+    id SERIAL PRIMARY KEY,
+    message_id VARCHAR (36) NOT NULL,
+    content VARCHAR (2000) NOT NULL,
+    edited_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+);
+
+CREATE INDEX message_revisions_message_idx ON message_revisions (message_id);
+
+ALTER TABLE messages ADD COLUMN edited_at TIMESTAMPTZ;
+ALTER TABLE messages ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE messages ADD COLUMN deleted_by VARCHAR (36) REFERENCES users(id) ON DELETE SET NULL;
\ No newline at end of file
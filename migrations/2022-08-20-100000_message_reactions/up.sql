@@ -0,0 +1,9 @@
+CREATE TABLE message_reactions (
+    message_id VARCHAR (36) NOT NULL,
+    user_id VARCHAR (36) NOT NULL,
+    emoji VARCHAR (32) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT UQ_message_reaction PRIMARY KEY (message_id, user_id, emoji)
+);
\ No newline at end of file
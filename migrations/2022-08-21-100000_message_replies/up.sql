@@ -0,0 +1,3 @@
+ALTER TABLE messages ADD COLUMN reply_to VARCHAR (36) REFERENCES messages(id) ON DELETE SET NULL;
+
+CREATE INDEX messages_reply_to_idx ON messages (reply_to);
\ No newline at end of file
@@ -1,5 +1,7 @@
 //! Contains the message models
-use actix::Message;
+use crate::actors::models::messages::error::SocketError;
+use actix::{Message, MessageResponse};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// The expected struct to use when sending and receiving chat messages. This is the actual
@@ -7,10 +9,14 @@ use serde::{Deserialize, Serialize};
 #[derive(Message, Debug, Serialize, Deserialize, Clone)]
 #[rtype(result = "()")]
 pub struct ChatMessage {
-    /// Each chat message has a specific id.
+    /// Each chat message has a specific id, assigned by the server. The ID the client sends
+    /// is echoed back as `client_ref`.
+    #[serde(default)]
     pub id: String,
     /// The session ID of the sender. Note that every session ID corresponds
     /// to a user in the database because they cannot use the chat unless they are logged in.
+    /// Checked against the session and filled in if omitted.
+    #[serde(default)]
     pub sender_id: String,
     /// The session ID of the receiver.
     pub receiver_id: String,
@@ -19,20 +25,49 @@ pub struct ChatMessage {
     /// Flag indicating whether the receiver has read the message. If it is a public message
     /// (i.e message sent to rooms with multiple receivers) this flag is omitted.
     pub read: bool,
+    /// When the server received the message
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// When the sender last edited the message
+    #[serde(default)]
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Deleted messages keep their place in the conversation without their content
+    #[serde(default)]
+    pub deleted: bool,
+    /// How many users reacted with each emoji
+    #[serde(default)]
+    pub reactions: Vec<ReactionCount>,
+    /// The ID of the message this one replies to, which has to be in the same conversation
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// How many replies the message got
+    #[serde(default)]
+    pub reply_count: i64,
+    /// The ID the sender gave the message, only sent back in the `chat_message` frame so
+    /// clients can match it with the message they displayed before it was confirmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ref: Option<String>,
 }
 
 /// Maps `id` to `room_id` in `ChatServer`'s rooms. Also reads messages. Returns all messages
 /// concerning the joining party and the party being joined
 #[derive(Message, Debug, Serialize, Deserialize, Clone)]
-#[rtype(result = "Vec<ChatMessage>")]
+#[rtype(result = "Result<Vec<ChatMessage>, SocketError>")]
 pub struct Join {
+    /// The joining user, checked against the session and filled in if omitted
+    #[serde(default)]
     pub id: String,
     pub room_id: String,
+    /// Required when joining a password protected room for the first time
+    #[serde(default)]
+    pub password: Option<String>,
 }
 /// Contains a vec of read messages.
 #[derive(Message, Debug, Serialize, Deserialize, Clone)]
 #[rtype(result = "()")]
 pub struct Read {
+    /// The user who read the messages
+    pub id: String,
     pub messages: Vec<ChatMessage>,
 }
 
@@ -40,6 +75,135 @@ pub struct Read {
 #[derive(Message, Debug, Serialize, Deserialize, Clone)]
 #[rtype(result = "()")]
 pub struct CreateRoom {
+    /// Checked against the session and filled in if omitted
+    #[serde(default)]
     pub sender_id: String,
     pub name: String,
+    /// Makes the room password protected if given
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Requests a page of the history `id` has with `room_id`, which is either a room or a user.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<MessagePage, SocketError>")]
+pub struct History {
+    /// The requesting user, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub room_id: String,
+    /// The ID of the oldest message the client already has. Omit it to get the latest page.
+    pub before: Option<String>,
+    pub limit: Option<i64>,
 }
+
+/// Requests a page of the replies to the message with the ID `message_id`.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<MessagePage, SocketError>")]
+pub struct Thread {
+    /// The requesting user, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub message_id: String,
+    /// The ID of the oldest reply the client already has. Omit it to get the latest page.
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// A page of a conversation's messages, oldest first.
+#[derive(MessageResponse, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MessagePage {
+    pub messages: Vec<ChatMessage>,
+    /// The cursor to pass as `before` to get the next (older) page. Omitted on the last page.
+    pub next: Option<String>,
+}
+
+/// Commands only the admin of a room can issue.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<(), SocketError>")]
+pub struct RoomAdmin {
+    /// The user issuing the command, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub room_id: String,
+    pub action: AdminAction,
+}
+
+/// The action the admin is taking on their room.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum AdminAction {
+    /// Removes the user with the given ID from the room
+    Kick(String),
+    /// Removes the user with the given ID from the room for good
+    Ban(String),
+    /// Gives the room a new name
+    Rename(String),
+    /// Deletes the room along with all its messages
+    Delete,
+    /// Hands admin rights to the member with the given ID
+    TransferAdmin(String),
+}
+
+/// Removes the user from the room's members.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<(), SocketError>")]
+pub struct Leave {
+    /// The user leaving, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub room_id: String,
+}
+
+/// Replaces the content of one of the user's messages.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<(), SocketError>")]
+pub struct EditMessage {
+    /// The user editing the message, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub message_id: String,
+    pub content: String,
+}
+
+/// Deletes one of the user's messages, or any message of a room they administer.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<(), SocketError>")]
+pub struct DeleteMessage {
+    /// The user deleting the message, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub message_id: String,
+}
+
+/// The amount of users that reacted to a message with the emoji.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+}
+
+/// Adds or removes the user's reaction to a message.
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "Result<(), SocketError>")]
+pub struct React {
+    /// The user reacting, always set from the session
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub message_id: String,
+    pub emoji: String,
+    /// Removes the reaction instead of adding it
+    #[serde(default)]
+    pub remove: bool,
+}
+
+/// Sent with the `reaction` header to everyone in the conversation when a user adds or
+/// removes a reaction.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReactionUpdate {
+    pub message_id: String,
+    pub user_id: String,
+    pub emoji: String,
+    pub removed: bool,
+    /// The message's reactions after the change
+    pub reactions: Vec<ReactionCount>,
+}
\ No newline at end of file
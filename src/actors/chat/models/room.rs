@@ -1,23 +1,42 @@
 use super::messages::ChatMessage;
 use actix::MessageResponse;
+use actix_web::web;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
+/// How many of the latest messages a room keeps in memory. Anything older is paged
+/// out of the database.
+pub const ROOM_MESSAGE_CACHE: usize = 50;
+
 #[derive(MessageResponse, Debug, Serialize, Deserialize, Clone)]
 pub struct PublicRoom {
     pub id: String,
     pub name: String,
     pub users: HashSet<String>,
     pub messages: Vec<ChatMessage>,
+    /// Whether joining the room requires a password
+    pub protected: bool,
+    /// The bcrypt hash of the room's password, never sent to clients
+    #[serde(skip)]
+    pub password: Option<String>,
+    /// The ID of the user allowed to administer the room
+    pub admin: Option<String>,
+    /// IDs of users who can't join the room
+    #[serde(skip)]
+    pub banned: HashSet<String>,
 }
 
 impl PublicRoom {
-    pub fn new_insert(id: &str, user_id: &str, name: &str) -> Self {
+    pub fn new_insert(id: &str, user_id: &str, name: &str, password: Option<String>) -> Self {
         let mut room = Self {
             id: id.to_string(),
             name: name.to_string(),
             users: HashSet::new(),
             messages: vec![],
+            protected: password.is_some(),
+            password,
+            admin: Some(user_id.to_string()),
+            banned: HashSet::new(),
         };
         let _ = room.users.insert(user_id.to_string());
         room
@@ -46,14 +65,92 @@ impl PublicRoom {
         false
     }
 
+    pub fn is_admin(&self, id: &str) -> bool {
+        self.admin.as_deref() == Some(id)
+    }
+
+    /// Removes the user from the room and keeps them from joining it again
+    pub fn ban_user(&mut self, id: &str) {
+        self.users.remove(id);
+        self.banned.insert(id.to_string());
+    }
+
+    pub fn is_banned(&self, id: &str) -> bool {
+        self.banned.contains(id)
+    }
+
+    /// Stores the message, dropping the oldest cached one if the cache is full
     pub fn store_message(&mut self, message: ChatMessage) {
         self.messages.push(message);
+        if self.messages.len() > ROOM_MESSAGE_CACHE {
+            self.messages.remove(0);
+        }
+    }
+}
+/// Hashes the room password on the blocking thread pool since bcrypt would hold up the
+/// chat server for hundreds of milliseconds.
+pub async fn hash_password(password: String) -> Result<String, String> {
+    match web::block(move || bcrypt::hash(password, bcrypt::DEFAULT_COST)).await {
+        Ok(Ok(hash)) => Ok(hash),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Checks the password against the room's hashed password on the blocking thread pool.
+/// Rooms without a password let everyone in.
+pub async fn verify_password(hash: Option<String>, password: Option<String>) -> bool {
+    match (hash, password) {
+        (None, _) => true,
+        (Some(hash), Some(password)) => matches!(
+            web::block(move || bcrypt::verify(password, &hash)).await,
+            Ok(Ok(true))
+        ),
+        (Some(_), None) => false,
+    }
+}
+
+/// What everyone gets to see of a room, regardless of whether they're a member.
+/// The room's messages are only sent to those who join it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoomSummary {
+    pub id: String,
+    pub name: String,
+    pub protected: bool,
+    pub admin: Option<String>,
+    pub user_count: usize,
+}
+
+impl From<&PublicRoom> for RoomSummary {
+    fn from(room: &PublicRoom) -> Self {
+        Self {
+            id: room.id.clone(),
+            name: room.name.clone(),
+            protected: room.protected,
+            admin: room.admin.clone(),
+            user_count: room.users.len(),
+        }
     }
 }
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum RoomData {
     Message(ChatMessage),
-    Room(PublicRoom),
-    Rooms(Vec<PublicRoom>),
+    Room(RoomSummary),
+    Rooms(Vec<RoomSummary>),
     Joined((String, String)),
+    /// The user was removed from the room by its admin, (user_id, room_id)
+    Kicked((String, String)),
+    /// The user was removed from the room and can't join it anymore, (user_id, room_id)
+    Banned((String, String)),
+    /// The room got a new name, (room_id, name)
+    Renamed((String, String)),
+    /// The room got a new admin, (room_id, admin_id)
+    AdminChanged((String, String)),
+    /// The room with the given ID no longer exists
+    Deleted(String),
+    /// The user left the room, (user_id, room_id)
+    Left((String, String)),
+    /// The room with the given ID had no members left and got archived
+    Archived(String),
 }
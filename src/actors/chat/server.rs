@@ -2,25 +2,48 @@
 //! and with whom the sessions are communicating.
 use super::models::{
     chat_user::ChatUser,
-    messages::{ChatMessage, CreateRoom, Join, Read},
-    room::{PublicRoom, RoomData},
+    messages::{
+        AdminAction, ChatMessage, CreateRoom, DeleteMessage, EditMessage, History, Join, Leave,
+        MessagePage, React, ReactionUpdate, Read, RoomAdmin, Thread,
+    },
+    room::{self, PublicRoom, RoomData, RoomSummary},
 };
 use crate::actors::{
     db::{
         manager::DBManager,
-        messages::{StoreChatMessage, StoreRoom, StoreRoomConnection},
+        messages::{
+            ArchiveRoom, ChatState, CountUnread, DeleteRoom, LoadChatState, LoadHistory,
+            LoadMessage, MarkRead, RemoveRoomConnection, RenameRoom, SetRoomAdmin,
+            SoftDeleteMessage, StoreChatMessage, StoreMessageEdit, StoreReaction, StoreRoom,
+            StoreRoomBan, StoreRoomConnection,
+        },
     },
     ez_handler,
     models::messages::{
-        client_message::{ClientMessage, MessageData, SocketMessage},
+        client_message::{ClientMessage, MessageData},
         connection::{Connect, Disconnect},
+        error::{ErrorCode, SocketError},
     },
+    models::sessions::Sessions,
 };
+use crate::models::message::{Conversation, UnreadCount};
 use actix::prelude::*;
+use chrono::Utc;
 use colored::Colorize;
 use serde::Serialize;
 use std::collections::HashMap;
-use tracing::info;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How many of the latest messages the chat server keeps in memory
+const MESSAGE_CACHE: usize = 500;
+/// How many of the latest messages get sent when joining a conversation, and the default
+/// page size of the history
+pub const HISTORY_PAGE_SIZE: i64 = 50;
+/// The most messages a client can request in a single page of history
+pub const MAX_PAGE_SIZE: i64 = 100;
+/// The longest a message can be, in characters. Matches the size of the content columns.
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
 
 /// `ChatServer` is an actor that manages chat rooms and is responsible for coordinating chat sessions.
 ///
@@ -28,12 +51,12 @@ use tracing::info;
 /// actor address (`sessions`) and which sessions are communicating with one another (`rooms`).
 /// It also keeps track of currently connected users and processes messages from other actors.
 pub struct ChatServer {
-    /// Sessions map a session ID with its actor address
-    sessions: HashMap<String, Recipient<SocketMessage>>,
+    /// Sessions map a session ID with the actor addresses of all its connections
+    sessions: Sessions,
     /// Maps session IDs to other session IDs
     id_pointers: HashMap<String, String>,
     public_rooms: HashMap<String, PublicRoom>,
-    /// Messages
+    /// The latest `MESSAGE_CACHE` messages, older ones are only kept in the database
     messages: Vec<ChatMessage>,
     /// The total connected users
     users: HashMap<String, ChatUser>,
@@ -44,7 +67,7 @@ pub struct ChatServer {
 impl ChatServer {
     pub fn new(db_manager: Addr<DBManager>) -> Self {
         Self {
-            sessions: HashMap::new(),
+            sessions: Sessions::default(),
             id_pointers: HashMap::new(),
             public_rooms: HashMap::new(),
             users: HashMap::new(),
@@ -55,64 +78,257 @@ impl ChatServer {
     /// Send a message to whoever the sender is pointing to
     fn send(&self, sender: &str, message: String) {
         if let Some(id) = self.id_pointers.get(sender) {
-            if let Some(address) = self.sessions.get(id) {
-                let _ = address.do_send(SocketMessage(message.clone()));
-            }
+            self.sessions.send(id, &message);
         }
     }
-    /// Directly send a message to the address of the given session ID.
+    /// Directly send a message to all connections of the given session ID.
     fn send_direct(&self, receiver: &str, message: String) {
-        if let Some(address) = self.sessions.get(receiver) {
-            let _ = address.do_send(SocketMessage(message.clone()));
-        }
+        self.sessions.send(receiver, &message);
     }
     /// Send a message to all actors
     fn broadcast(&self, message: String) {
         info!("{}{:?}", "BROADCASTING : ".blue(), message);
-        for address in self.sessions.values() {
-            address.do_send(SocketMessage(message.clone()));
-        }
+        self.sessions.broadcast(&message);
     }
 
     /// Send a message to all users in a specific room
-    fn _room_broadcast(&self, room_id: &str, message: String) {
+    fn room_broadcast(&self, room_id: &str, message: String) {
         if let Some(room) = self.public_rooms.get(room_id) {
             for user_id in room.get_user_ids() {
-                if let Some(address) = self.sessions.get(&user_id) {
-                    address.do_send(SocketMessage(message.clone()))
-                }
+                self.sessions.send(&user_id, &message);
+            }
+        }
+    }
+
+    /// Caches the message, dropping the oldest cached one if the cache is full
+    fn store_message(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+        if self.messages.len() > MESSAGE_CACHE {
+            self.messages.remove(0);
+        }
+    }
+
+    /// Returns the message with the given ID from the cache, or from the database if it's
+    /// too old to be cached
+    fn find_message(&self, message_id: &str) -> ResponseActFuture<Self, Option<ChatMessage>> {
+        if let Some(message) = self
+            .messages
+            .iter()
+            .find(|message| message.id == message_id)
+        {
+            return Box::pin(fut::ready(Some(message.clone())));
+        }
+        Box::pin(
+            self.db_manager
+                .send(LoadMessage {
+                    message_id: message_id.to_string(),
+                })
+                .into_actor(self)
+                .map(|res, _, _| res.ok().flatten()),
+        )
+    }
+
+    /// Replaces the cached copies of the message and sends it with the given header to
+    /// everyone in its conversation
+    fn update_message(&mut self, message: ChatMessage, header: &str) {
+        self.cache_update(&message);
+        let frame = ez_handler::generate_message::<ChatMessage>(
+            header,
+            MessageData::ChatMessage(message.clone()),
+        )
+        .unwrap();
+        self.conversation_send(&message, frame);
+    }
+
+    /// Replaces the cached copies of the message, if it's recent enough to be cached
+    fn cache_update(&mut self, message: &ChatMessage) {
+        if let Some(cached) = self.messages.iter_mut().find(|m| m.id == message.id) {
+            *cached = message.clone();
+        }
+        if let Some(room) = self.public_rooms.get_mut(&message.receiver_id) {
+            if let Some(cached) = room.messages.iter_mut().find(|m| m.id == message.id) {
+                *cached = message.clone();
+            }
+        }
+    }
+
+    /// Changes the reply count of the cached copies of the message by `change`
+    fn count_reply(&mut self, message_id: &str, change: i64) {
+        let rooms = self
+            .public_rooms
+            .values_mut()
+            .flat_map(|room| room.messages.iter_mut());
+        for cached in self.messages.iter_mut().chain(rooms) {
+            if cached.id == message_id {
+                cached.reply_count = (cached.reply_count + change).max(0);
             }
         }
     }
 
-    /// Gets all messages sent and received between the two given ids. If they are private messages and
-    /// `id` is the receiver of those messages, read them.
-    fn get_messages(&mut self, id: &str, other_id: &str) -> Vec<ChatMessage> {
-        let mut messages = vec![];
-        for message in &mut self.messages {
-            // If the user is the receiver read the message
+    /// Returns true if both messages were sent to the same room, or between the same two users
+    fn same_conversation(&self, a: &ChatMessage, b: &ChatMessage) -> bool {
+        if self.public_rooms.contains_key(&a.receiver_id) {
+            return a.receiver_id == b.receiver_id;
+        }
+        (a.sender_id == b.sender_id && a.receiver_id == b.receiver_id)
+            || (a.sender_id == b.receiver_id && a.receiver_id == b.sender_id)
+    }
+
+    /// Returns an error if the content of a message is empty or too long
+    fn check_content(content: &str, header: &str) -> Result<(), SocketError> {
+        if content.trim().is_empty() || content.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(SocketError::new(
+                ErrorCode::SchemaMismatch,
+                Some(header),
+                format!("Messages are 1 to {} characters long", MAX_MESSAGE_LENGTH),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns true if `id` is allowed to see the message's conversation
+    fn can_see(&self, id: &str, message: &ChatMessage) -> bool {
+        match self.public_rooms.get(&message.receiver_id) {
+            Some(room) => !room.is_banned(id) && (!room.protected || room.has_user(id)),
+            None => message.sender_id == id || message.receiver_id == id,
+        }
+    }
+
+    /// Sends the frame to the members of the message's room, or to both users of a private
+    /// conversation
+    fn conversation_send(&self, message: &ChatMessage, frame: String) {
+        if self.public_rooms.contains_key(&message.receiver_id) {
+            self.room_broadcast(&message.receiver_id, frame);
+            return;
+        }
+        self.send_direct(&message.sender_id, frame.clone());
+        if message.receiver_id != message.sender_id {
+            self.send_direct(&message.receiver_id, frame);
+        }
+    }
+
+    /// Reads all private messages `other_id` sent to `id`, both in the given history
+    /// and in the cache. Returns the IDs of the messages in the history that weren't read before.
+    fn read_messages(
+        &mut self,
+        id: &str,
+        other_id: &str,
+        history: &mut [ChatMessage],
+    ) -> Vec<String> {
+        let mut unread = vec![];
+        for message in history.iter_mut() {
+            if message.receiver_id == id && message.sender_id == other_id && !message.read {
+                unread.push(message.id.clone());
+            }
+        }
+        for message in history.iter_mut().chain(self.messages.iter_mut()) {
             if message.receiver_id == id && message.sender_id == other_id {
-                if message.read == false {
-                    message.read = true;
-                }
-                messages.push(message.clone());
-                continue;
+                message.read = true;
             }
-            // Otherwise just prepare it for sending
-            if message.receiver_id == other_id && message.sender_id == id {
-                messages.push(message.clone());
+        }
+        unread
+    }
+
+    /// Picks up where the server left off before it was restarted
+    fn restore(&mut self, state: ChatState) {
+        info!(
+            "{}{} rooms, {} messages",
+            "RESTORING CHAT STATE : ".green(),
+            state.rooms.len(),
+            state.messages.len()
+        );
+        for room in state.rooms {
+            self.public_rooms.insert(room.id.clone(), room);
+        }
+        for message in state.messages {
+            self.store_message(message);
+        }
+    }
+
+    /// Loads a page of the history `id` has with `room_id` from the database. Private messages
+    /// `id` received in the page get read.
+    fn load_history(
+        &self,
+        id: String,
+        room_id: String,
+        before: Option<String>,
+        limit: i64,
+    ) -> ResponseActFuture<Self, MessagePage> {
+        let is_room = self.public_rooms.contains_key(&room_id);
+        let conversation = if is_room {
+            Conversation::Room(room_id.clone())
+        } else {
+            Conversation::Direct(id.clone(), room_id.clone())
+        };
+        Box::pin(
+            self.db_manager
+                .send(LoadHistory {
+                    conversation,
+                    before,
+                    limit,
+                })
+                .into_actor(self)
+                .map(move |res, act, _| {
+                    let mut page = res.unwrap_or_default();
+                    if !is_room {
+                        let unread = act.read_messages(&id, &room_id, &mut page.messages);
+                        if !unread.is_empty() {
+                            act.db_manager.do_send(MarkRead {
+                                receiver_id: id.clone(),
+                                message_ids: unread,
+                            });
+                        }
+                        // Messages sent to self are automatically read
+                        if id != room_id && !page.messages.is_empty() {
+                            act.send_direct(
+                                &room_id,
+                                ez_handler::generate_message(
+                                    "read",
+                                    MessageData::List(page.messages.clone()),
+                                )
+                                .unwrap(),
+                            );
+                        }
+                    }
+                    page
+                }),
+        )
+    }
+
+    /// Points the user back to themselves if they were pointing to the given room.
+    fn reset_pointer(&mut self, id: &str, room_id: &str) {
+        if let Some(pointer) = self.id_pointers.get_mut(id) {
+            if pointer == room_id {
+                *pointer = id.to_string();
             }
         }
-        messages
     }
 
-    /// Returns all registered public rooms in a vec
-    fn get_rooms(&self) -> Vec<PublicRoom> {
-        self.public_rooms.values().cloned().collect()
+    /// Protected rooms are only accessible to their members and no room is accessible to
+    /// the users banned from it.
+    fn check_membership(&self, id: &str, room_id: &str, header: &str) -> Result<(), SocketError> {
+        match self.public_rooms.get(room_id) {
+            Some(room) if room.is_banned(id) => Err(SocketError::new(
+                ErrorCode::Forbidden,
+                Some(header),
+                "You are banned from this room",
+            )),
+            Some(room) if room.protected && !room.has_user(id) => Err(SocketError::new(
+                ErrorCode::Forbidden,
+                Some(header),
+                "Join the room with its password first",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a summary of all registered public rooms in a vec
+    fn get_rooms(&self) -> Vec<RoomSummary> {
+        self.public_rooms.values().map(RoomSummary::from).collect()
     }
 
     /// Clean empty id_pointers
-    fn clean_rooms(&mut self) {
+    fn clean_pointers(&mut self) {
         for room in self.id_pointers.clone().into_keys() {
             if let Some(sessions) = self.id_pointers.get(&room) {
                 if sessions.is_empty() {
@@ -121,25 +337,59 @@ impl ChatServer {
             }
         }
     }
+
+    /// Archives the public room if its last member just left it
+    fn archive_if_empty(&mut self, room_id: &str) {
+        match self.public_rooms.get(room_id) {
+            Some(room) if room.users.is_empty() => {}
+            _ => return,
+        }
+        info!("{}{}", "ARCHIVING ROOM : ".cyan(), room_id);
+        self.public_rooms.remove(room_id);
+        self.messages
+            .retain(|message| message.receiver_id != room_id);
+        self.db_manager.do_send(ArchiveRoom {
+            room_id: room_id.to_string(),
+        });
+        self.broadcast(
+            ez_handler::generate_message::<RoomData>(
+                "room",
+                MessageData::Room(RoomData::Archived(room_id.to_string())),
+            )
+            .unwrap(),
+        );
+    }
 }
 
 /// Make actor from `ChatServer`
 impl Actor for ChatServer {
     type Context = Context<Self>;
-    fn started(&mut self, _ctx: &mut Context<Self>) {
+    /// Restores the persisted rooms and messages before handling any other message.
+    fn started(&mut self, ctx: &mut Context<Self>) {
         info!("{}", "Started Chat Server".green());
+        self.db_manager
+            .send(LoadChatState {
+                message_limit: MESSAGE_CACHE as i64,
+            })
+            .into_actor(self)
+            .map(|res, act, _| match res {
+                Ok(state) => act.restore(state),
+                Err(e) => warn!("{}{:?}", "COULDN'T RESTORE CHAT STATE : ".red(), e),
+            })
+            .wait(ctx);
     }
 }
 
 /// Message received upon connection with client. Registers the user if they are new,
-/// otherwise sets their status to connected. Sends a global message with the connecting user's data and
-/// sends the following to the connecting user:
+/// otherwise sets their status to connected. Sends a global message with the connecting user's data
+/// if it's their first open connection and sends the following to the new connection:
 /// - session
 /// - users
-/// - messages
+/// - rooms
+/// - the number of unread messages from each sender
 impl Handler<Connect> for ChatServer {
     type Result = ();
-    fn handle(&mut self, msg: Connect, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: Connect, ctx: &mut Context<Self>) -> Self::Result {
         info!("{}{:?}", "USER CONNECTED : ".green(), msg.user);
 
         if let Some(user) = self.users.get_mut(&msg.user.id) {
@@ -148,64 +398,93 @@ impl Handler<Connect> for ChatServer {
             self.users.insert(msg.user.id.clone(), msg.user.clone());
         }
 
-        // Notify all users
-        self.broadcast(
-            ez_handler::generate_message::<ChatUser>(
-                "user_connected",
-                MessageData::User(msg.user.clone()),
-            )
-            .unwrap(),
-        );
+        // Notify all users if this is the user's first connection
+        if !self.sessions.is_connected(&msg.user.id) {
+            self.broadcast(
+                ez_handler::generate_message::<ChatUser>(
+                    "user_connected",
+                    MessageData::User(msg.user.clone()),
+                )
+                .unwrap(),
+            );
+        }
 
         // Insert into session
         let id = msg.user.id.clone();
-        self.sessions.insert(id.clone(), msg.address);
+        let connection_id = msg.connection_id.clone();
+        self.sessions.insert(&id, &connection_id, msg.address);
         self.id_pointers
             .entry(id.to_owned())
             .or_insert_with(|| id.clone());
 
-        // Send session string to self
-        self.send_direct(
+        // Send session string to the new connection
+        self.sessions.send_to(
             &id,
-            ez_handler::generate_message::<String>("session", MessageData::String(id.clone()))
+            &connection_id,
+            &ez_handler::generate_message::<String>("session", MessageData::String(id.clone()))
                 .unwrap(),
         );
 
-        // Send all users to self
-        self.send_direct(
+        // Send all users to the new connection
+        self.sessions.send_to(
             &id,
-            ez_handler::generate_message(
+            &connection_id,
+            &ez_handler::generate_message(
                 "users",
                 MessageData::List(self.users.clone().into_values().collect()),
             )
             .unwrap(),
         );
 
-        // Send all public rooms to self
+        // Send all public rooms to the new connection
         if self.public_rooms.len() > 0 {
-            self.send_direct(
+            self.sessions.send_to(
                 &id,
-                ez_handler::generate_message::<RoomData>(
+                &connection_id,
+                &ez_handler::generate_message::<RoomData>(
                     "room",
                     MessageData::Room(RoomData::Rooms(self.get_rooms())),
                 )
                 .unwrap(),
             );
         }
+
+        // Send the unread message counts to the new connection
+        self.db_manager
+            .send(CountUnread {
+                user_id: id.clone(),
+            })
+            .into_actor(self)
+            .map(move |res, act, _| match res {
+                Ok(counts) => act.sessions.send_to(
+                    &id,
+                    &connection_id,
+                    &ez_handler::generate_message::<UnreadCount>(
+                        "unread",
+                        MessageData::List(counts),
+                    )
+                    .unwrap(),
+                ),
+                Err(e) => warn!("{}{:?}", "COULDN'T COUNT UNREAD MESSAGES : ".red(), e),
+            })
+            .spawn(ctx);
     }
 }
 
-/// Message received when an actor gets dropped. Sets users' connected status to false.
-/// Sends a global message with the disconnecting user's ID and removes their room entry.
+/// Message received when an actor gets dropped. When the user's last connection closes, sets
+/// their connected status to false, sends a global message with their ID and removes their
+/// room entry.
 impl Handler<Disconnect> for ChatServer {
     type Result = ();
 
     fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
         info!("{}{:?}", "USER DISCONNECTED : ".red(), msg);
 
-        if self.sessions.remove(&msg.session_id).is_some() {
-            self.id_pointers.remove(&msg.session_id);
+        // The user is still connected elsewhere
+        if !self.sessions.remove(&msg.session_id, &msg.connection_id) {
+            return;
         }
+        self.id_pointers.remove(&msg.session_id);
 
         if let Some(user) = self.users.get_mut(&msg.session_id) {
             user.connected = false;
@@ -219,7 +498,7 @@ impl Handler<Disconnect> for ChatServer {
             .unwrap(),
         );
 
-        self.clean_rooms();
+        self.clean_pointers();
     }
 }
 
@@ -228,56 +507,161 @@ impl Handler<Disconnect> for ChatServer {
 impl<T: Serialize> Handler<ClientMessage<T>> for ChatServer {
     type Result = ();
 
-    fn handle(&mut self, message: ClientMessage<T>, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, message: ClientMessage<T>, ctx: &mut Context<Self>) -> Self::Result {
         if let MessageData::ChatMessage(msg) = message.data {
-            // Push it to the in memory store
-            self.messages.push(msg.clone());
-
-            let message = ez_handler::generate_message::<ChatMessage>(
-                "chat_message",
-                MessageData::ChatMessage(msg.clone()),
-            )
-            .unwrap();
+            // The server is the authority on IDs, time and everything that happens to the
+            // message afterwards, the client's ID is only echoed back
+            let client_ref = Some(msg.id).filter(|id| !id.is_empty());
+            let msg = ChatMessage {
+                id: Uuid::new_v4().to_string(),
+                timestamp: Some(Utc::now()),
+                edited_at: None,
+                deleted: false,
+                reactions: vec![],
+                reply_count: 0,
+                client_ref: None,
+                ..msg
+            };
 
-            // Store it and return if it's intended for a room
-            if let Some(room) = self.public_rooms.get_mut(&msg.receiver_id) {
-                room.store_message(msg.clone());
+            if let Err(e) = self
+                .check_membership(&msg.sender_id, &msg.receiver_id, "chat_message")
+                .and_then(|_| Self::check_content(&msg.content, "chat_message"))
+            {
+                self.send_direct(
+                    &msg.sender_id,
+                    ez_handler::generate_message::<String>("error", MessageData::Error(e)).unwrap(),
+                );
+                return;
             }
-            if let Some(room) = self.public_rooms.get(&msg.receiver_id) {
-                for user_id in self.users.keys() {
-                    if let Some(receiver) = self.id_pointers.get(user_id) {
-                        if receiver.eq(&room.id) {
-                            self.send_direct(user_id, message.clone())
-                        }
+
+            let parent_id = match msg.reply_to.clone() {
+                Some(parent_id) => parent_id,
+                None => return self.deliver_message(msg, client_ref),
+            };
+            // Replies have to stay in the conversation of the message they reply to
+            self.find_message(&parent_id)
+                .map(move |parent, act, _| {
+                    if !parent.is_some_and(|parent| act.same_conversation(&parent, &msg)) {
+                        act.send_direct(
+                            &msg.sender_id,
+                            ez_handler::generate_message::<String>(
+                                "error",
+                                MessageData::Error(SocketError::new(
+                                    ErrorCode::NotFound,
+                                    Some("chat_message"),
+                                    "The message replied to isn't part of this conversation",
+                                )),
+                            )
+                            .unwrap(),
+                        );
+                        return;
+                    }
+                    act.deliver_message(msg, client_ref);
+                })
+                .wait(ctx);
+        }
+    }
+}
+
+impl ChatServer {
+    /// Caches, stores and sends the message to its receivers
+    fn deliver_message(&mut self, msg: ChatMessage, client_ref: Option<String>) {
+        if let Some(parent_id) = &msg.reply_to {
+            self.count_reply(parent_id, 1);
+        }
+
+        // Push it to the in memory store
+        self.store_message(msg.clone());
+
+        let message = ez_handler::generate_message::<ChatMessage>(
+            "chat_message",
+            MessageData::ChatMessage(ChatMessage {
+                client_ref,
+                ..msg.clone()
+            }),
+        )
+        .unwrap();
+
+        // Store it and return if it's intended for a room
+        if let Some(room) = self.public_rooms.get_mut(&msg.receiver_id) {
+            room.store_message(msg.clone());
+        }
+        if let Some(room) = self.public_rooms.get(&msg.receiver_id) {
+            for user_id in self.users.keys() {
+                if let Some(receiver) = self.id_pointers.get(user_id) {
+                    if receiver.eq(&room.id) {
+                        self.send_direct(user_id, message.clone())
                     }
                 }
-                self.db_manager.do_send(StoreChatMessage {
-                    message: msg,
-                    is_room: true,
-                });
-                return;
-            } else {
-                self.db_manager.do_send(StoreChatMessage {
-                    message: msg.clone(),
-                    is_room: false,
-                });
-                // Send it only if it's not being sent to self
-                if msg.receiver_id != msg.sender_id {
-                    self.send(&msg.sender_id, message.clone());
-                }
-                self.send_direct(&&msg.sender_id, message);
             }
+            self.db_manager.do_send(StoreChatMessage {
+                message: msg,
+                is_room: true,
+            });
+            return;
+        } else {
+            self.db_manager.do_send(StoreChatMessage {
+                message: msg.clone(),
+                is_room: false,
+            });
+            // Send it only if it's not being sent to self
+            if msg.receiver_id != msg.sender_id {
+                self.send(&msg.sender_id, message.clone());
+            }
+            self.send_direct(&&msg.sender_id, message);
         }
     }
 }
 
+/// Points the user to the room, returns the latest page of the conversation's history from
+/// the database. Room passwords are checked off the actor before the user joins.
 impl Handler<Join> for ChatServer {
-    type Result = Vec<ChatMessage>;
+    type Result = ResponseActFuture<Self, Result<Vec<ChatMessage>, SocketError>>;
 
     fn handle(&mut self, message: Join, _: &mut Context<Self>) -> Self::Result {
-        let Join { id, room_id } = message;
+        let Join {
+            id,
+            room_id,
+            password,
+        } = message;
         info!("{}{}{}{}", "JOINING : ".cyan(), id, " => ".cyan(), room_id);
 
+        // Members of protected rooms only need the password the first time they join
+        let hash = match self.public_rooms.get(&room_id) {
+            Some(public_room) if public_room.is_banned(&id) => {
+                return Box::pin(fut::ready(Err(SocketError::new(
+                    ErrorCode::Forbidden,
+                    Some("join"),
+                    "You are banned from this room",
+                ))));
+            }
+            Some(public_room) if !public_room.has_user(&id) => public_room.password.clone(),
+            _ => None,
+        };
+
+        let verify = room::verify_password(hash, password).into_actor(self);
+        Box::pin(verify.then(move |verified, act, _| -> Self::Result {
+            if !verified {
+                warn!("{}{} => {}", "WRONG ROOM PASSWORD : ".red(), id, room_id);
+                return Box::pin(fut::ready(Err(SocketError::new(
+                    ErrorCode::WrongPassword,
+                    Some("join"),
+                    "Wrong room password",
+                ))));
+            }
+            act.join_room(id, room_id)
+        }))
+    }
+}
+
+impl ChatServer {
+    /// Points the user to the room, adding them to its members if it's a public room they
+    /// aren't part of yet.
+    fn join_room(
+        &mut self,
+        id: String,
+        room_id: String,
+    ) -> ResponseActFuture<Self, Result<Vec<ChatMessage>, SocketError>> {
         // Set the sender to point to the receiver
         self.id_pointers.remove(&id);
         self.id_pointers
@@ -298,69 +682,558 @@ impl Handler<Join> for ChatServer {
             }
         }
 
-        // If joining a public room return its messages
+        // If joining a public room store the connection
         if let Some(public_room) = self.public_rooms.get(&room_id) {
             self.db_manager.do_send(StoreRoomConnection {
                 room_id: public_room.id.clone(),
-                user_id: id,
+                user_id: id.clone(),
             });
-            return public_room.get_messages();
         }
 
-        // Get all associated messages
-        let messages = self.get_messages(&id, &room_id);
+        Box::pin(
+            self.load_history(id, room_id, None, HISTORY_PAGE_SIZE)
+                .map(|page, _, _| Ok(page.messages)),
+        )
+    }
+}
+
+/// Returns the requested page of history from the database.
+impl Handler<History> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<MessagePage, SocketError>>;
 
-        // Messages sent to self are automatically read
-        if id != room_id && messages.len() > 0 {
-            self.send(
-                &id,
-                ez_handler::generate_message("read", MessageData::List(messages.clone())).unwrap(),
-            );
+    fn handle(&mut self, message: History, _: &mut Context<Self>) -> Self::Result {
+        let History {
+            id,
+            room_id,
+            before,
+            limit,
+        } = message;
+        if let Err(e) = self.check_membership(&id, &room_id, "history") {
+            return Box::pin(fut::ready(Err(e)));
         }
-        messages
+        let limit = limit.unwrap_or(HISTORY_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        Box::pin(
+            self.load_history(id, room_id, before, limit)
+                .map(|page, _, _| Ok(page)),
+        )
+    }
+}
+
+/// Returns the requested page of a message's replies from the database.
+impl Handler<Thread> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<MessagePage, SocketError>>;
+
+    fn handle(&mut self, message: Thread, _: &mut Context<Self>) -> Self::Result {
+        let Thread {
+            id,
+            message_id,
+            before,
+            limit,
+        } = message;
+        let limit = limit.unwrap_or(HISTORY_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let error = |code, message| -> Result<MessagePage, SocketError> {
+            Err(SocketError::new(code, Some("thread"), message))
+        };
+
+        let lookup = self.find_message(&message_id);
+        let load = move |found: Option<ChatMessage>, act: &mut Self, _: &mut _| -> Self::Result {
+            let parent = match found {
+                Some(parent) => parent,
+                None => return Box::pin(fut::ready(error(ErrorCode::NotFound, "No such message"))),
+            };
+            if !act.can_see(&id, &parent) {
+                return Box::pin(fut::ready(error(
+                    ErrorCode::Forbidden,
+                    "You can only read threads of your conversations",
+                )));
+            }
+            Box::pin(
+                act.db_manager
+                    .send(LoadHistory {
+                        conversation: Conversation::Thread(message_id),
+                        before,
+                        limit,
+                    })
+                    .into_actor(act)
+                    .map(|res, _, _| Ok(res.unwrap_or_default())),
+            )
+        };
+        Box::pin(lookup.then(load))
     }
 }
 
+/// Marks the private messages read. Only the messages the user actually received and
+/// hadn't read yet get marked, their senders are notified with the `read` header.
 impl Handler<Read> for ChatServer {
     type Result = ();
-    fn handle(&mut self, message: Read, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, message: Read, ctx: &mut Context<Self>) -> Self::Result {
         info!("{}{:?}", "READING MESSAGES : ".cyan(), message.messages);
-        let mut sender = String::new();
-        for message in message.messages.clone() {
-            let ChatMessage { id, sender_id, .. } = message;
-            sender = sender_id;
-            for i in 0..self.messages.len() {
-                if id == self.messages[i].id {
-                    self.messages[i].read = true;
-                    break;
+        let message_ids = message
+            .messages
+            .into_iter()
+            .map(|read_message| read_message.id)
+            .collect();
+        self.db_manager
+            .send(MarkRead {
+                receiver_id: message.id,
+                message_ids,
+            })
+            .into_actor(self)
+            .map(|res, act, _| {
+                let mut by_sender: HashMap<String, Vec<ChatMessage>> = HashMap::new();
+                for read_message in res.unwrap_or_default() {
+                    if let Some(cached) = act.messages.iter_mut().find(|m| m.id == read_message.id)
+                    {
+                        cached.read = true;
+                    }
+                    by_sender
+                        .entry(read_message.sender_id.clone())
+                        .or_default()
+                        .push(read_message);
                 }
-            }
-        }
-        self.send_direct(
-            sender.as_ref(),
-            ez_handler::generate_message("read", MessageData::List(message.messages)).unwrap(),
-        );
+                for (sender_id, messages) in by_sender {
+                    act.send_direct(
+                        &sender_id,
+                        ez_handler::generate_message("read", MessageData::List(messages)).unwrap(),
+                    );
+                }
+            })
+            .spawn(ctx);
     }
 }
 
-/// Creates and stores a public room then broadcasts it to everyone connected
+/// Creates and stores a public room then broadcasts it to everyone connected. The password
+/// is hashed off the actor so the chat keeps going in the meantime.
 impl Handler<CreateRoom> for ChatServer {
     type Result = ();
-    fn handle(&mut self, message: CreateRoom, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, message: CreateRoom, ctx: &mut Context<Self>) -> Self::Result {
         info!("{}{:?}", "CREATING ROOM WITH : ".cyan(), message.sender_id);
+        let CreateRoom {
+            sender_id,
+            name,
+            password,
+        } = message;
+        let hashing = async move {
+            match password {
+                Some(password) => room::hash_password(password).await.map(Some),
+                None => Ok(None),
+            }
+        };
+        hashing
+            .into_actor(self)
+            .map(move |res, act, _| match res {
+                Ok(password) => act.create_room(&sender_id, &name, password),
+                Err(e) => warn!("{}{:?}", "COULDN'T HASH ROOM PASSWORD : ".red(), e),
+            })
+            .spawn(ctx);
+    }
+}
+
+impl ChatServer {
+    /// Registers the room with its creator as admin and only member
+    fn create_room(&mut self, sender_id: &str, name: &str, password: Option<String>) {
         let id = uuid::Uuid::new_v4().to_string();
-        let room = PublicRoom::new_insert(&id, &message.sender_id, &message.name);
+        let room = PublicRoom::new_insert(&id, sender_id, name, password);
         self.public_rooms.insert(id.clone(), room.clone());
         self.db_manager.do_send(StoreRoom {
             room: room.clone(),
-            admin_id: message.sender_id,
+            admin_id: sender_id.to_string(),
+        });
+        self.db_manager.do_send(StoreRoomConnection {
+            room_id: id,
+            user_id: sender_id.to_string(),
         });
         self.broadcast(
             ez_handler::generate_message::<RoomData>(
                 "room",
-                MessageData::Room(RoomData::Room(room)),
+                MessageData::Room(RoomData::Room(RoomSummary::from(&room))),
             )
             .unwrap(),
         );
     }
 }
+
+/// Lets the admin of a room manage it. Every change is sent to the room's members as a
+/// `RoomData` event. Renames and deletions are sent to everyone since all users see the
+/// room list.
+impl Handler<RoomAdmin> for ChatServer {
+    type Result = Result<(), SocketError>;
+
+    fn handle(&mut self, message: RoomAdmin, _: &mut Context<Self>) -> Self::Result {
+        let RoomAdmin {
+            id,
+            room_id,
+            action,
+        } = message;
+        info!("{}{} => {:?}", "ROOM ADMIN : ".cyan(), id, action);
+        let error = |code, message| Err(SocketError::new(code, Some("room_admin"), message));
+
+        let room = match self.public_rooms.get(&room_id) {
+            Some(room) => room,
+            None => return error(ErrorCode::NotFound, "No such room"),
+        };
+        if !room.is_admin(&id) {
+            return error(ErrorCode::Forbidden, "Only the room's admin can do that");
+        }
+
+        match action {
+            AdminAction::Kick(user_id) => {
+                if user_id == id {
+                    return error(ErrorCode::Forbidden, "Admins can't kick themselves");
+                }
+                if !room.has_user(&user_id) {
+                    return error(ErrorCode::NotFound, "The user isn't in the room");
+                }
+                self.room_broadcast(
+                    &room_id,
+                    ez_handler::generate_message::<RoomData>(
+                        "room",
+                        MessageData::Room(RoomData::Kicked((user_id.clone(), room_id.clone()))),
+                    )
+                    .unwrap(),
+                );
+                if let Some(room) = self.public_rooms.get_mut(&room_id) {
+                    room.remove_user(&user_id);
+                }
+                self.reset_pointer(&user_id, &room_id);
+                self.db_manager.do_send(RemoveRoomConnection {
+                    room_id: room_id.clone(),
+                    user_id,
+                });
+                self.archive_if_empty(&room_id);
+            }
+            AdminAction::Ban(user_id) => {
+                if user_id == id {
+                    return error(ErrorCode::Forbidden, "Admins can't ban themselves");
+                }
+                let message = ez_handler::generate_message::<RoomData>(
+                    "room",
+                    MessageData::Room(RoomData::Banned((user_id.clone(), room_id.clone()))),
+                )
+                .unwrap();
+                // Let the user know even if they aren't a member
+                if !room.has_user(&user_id) {
+                    self.send_direct(&user_id, message.clone());
+                }
+                self.room_broadcast(&room_id, message);
+                if let Some(room) = self.public_rooms.get_mut(&room_id) {
+                    room.ban_user(&user_id);
+                }
+                self.reset_pointer(&user_id, &room_id);
+                self.db_manager.do_send(StoreRoomBan {
+                    room_id: room_id.clone(),
+                    user_id: user_id.clone(),
+                });
+                self.db_manager
+                    .do_send(RemoveRoomConnection { room_id, user_id });
+            }
+            AdminAction::Rename(name) => {
+                let name = name.trim().to_string();
+                if name.is_empty() || name.chars().count() > 30 {
+                    return error(
+                        ErrorCode::SchemaMismatch,
+                        "Room names are 1 to 30 characters long",
+                    );
+                }
+                if let Some(room) = self.public_rooms.get_mut(&room_id) {
+                    room.name = name.clone();
+                }
+                self.broadcast(
+                    ez_handler::generate_message::<RoomData>(
+                        "room",
+                        MessageData::Room(RoomData::Renamed((room_id.clone(), name.clone()))),
+                    )
+                    .unwrap(),
+                );
+                self.db_manager.do_send(RenameRoom { room_id, name });
+            }
+            AdminAction::Delete => {
+                if let Some(room) = self.public_rooms.remove(&room_id) {
+                    for user_id in room.get_user_ids() {
+                        self.reset_pointer(&user_id, &room_id);
+                    }
+                }
+                self.messages
+                    .retain(|message| message.receiver_id != room_id);
+                self.broadcast(
+                    ez_handler::generate_message::<RoomData>(
+                        "room",
+                        MessageData::Room(RoomData::Deleted(room_id.clone())),
+                    )
+                    .unwrap(),
+                );
+                self.db_manager.do_send(DeleteRoom { room_id });
+            }
+            AdminAction::TransferAdmin(user_id) => {
+                if !room.has_user(&user_id) {
+                    return error(ErrorCode::NotFound, "The user isn't in the room");
+                }
+                if let Some(room) = self.public_rooms.get_mut(&room_id) {
+                    room.admin = Some(user_id.clone());
+                }
+                self.room_broadcast(
+                    &room_id,
+                    ez_handler::generate_message::<RoomData>(
+                        "room",
+                        MessageData::Room(RoomData::AdminChanged((
+                            room_id.clone(),
+                            user_id.clone(),
+                        ))),
+                    )
+                    .unwrap(),
+                );
+                self.db_manager.do_send(SetRoomAdmin {
+                    room_id,
+                    admin_id: user_id,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lets users edit their own messages. The previous content is kept as a revision.
+impl Handler<EditMessage> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<(), SocketError>>;
+
+    fn handle(&mut self, message: EditMessage, _: &mut Context<Self>) -> Self::Result {
+        let EditMessage {
+            id,
+            message_id,
+            content,
+        } = message;
+        info!("{}{} => {}", "EDITING MESSAGE : ".cyan(), id, message_id);
+        Box::pin(self.find_message(&message_id).then(move |found, act, _| -> Self::Result {
+            let error = |code, message| -> Self::Result {
+                Box::pin(fut::ready(Err(SocketError::new(
+                    code,
+                    Some("edit_message"),
+                    message,
+                ))))
+            };
+            let mut message = match found {
+                Some(message) if !message.deleted => message,
+                _ => return error(ErrorCode::NotFound, "No such message"),
+            };
+            if message.sender_id != id {
+                return error(ErrorCode::Forbidden, "You can only edit your own messages");
+            }
+            if act
+                .public_rooms
+                .get(&message.receiver_id)
+                .is_some_and(|room| room.is_banned(&id))
+            {
+                return error(ErrorCode::Forbidden, "You are banned from this room");
+            }
+            if let Err(e) = Self::check_content(&content, "edit_message") {
+                return Box::pin(fut::ready(Err(e)));
+            }
+
+            // Only sent once the edit and its revision are stored
+            let previous = std::mem::replace(&mut message.content, content);
+            let edited_at = Utc::now();
+            message.edited_at = Some(edited_at);
+            Box::pin(
+                act.db_manager
+                    .send(StoreMessageEdit {
+                        message_id,
+                        previous,
+                        content: message.content.clone(),
+                        edited_at,
+                    })
+                    .into_actor(act)
+                    .map(move |stored, act, _| {
+                        if !matches!(stored, Ok(true)) {
+                            return Err(SocketError::new(
+                                ErrorCode::NotFound,
+                                Some("edit_message"),
+                                "Couldn't edit the message",
+                            ));
+                        }
+                        act.update_message(message, "message_edited");
+                        Ok(())
+                    }),
+            )
+        }))
+    }
+}
+
+/// Soft deletes a message. Users can delete their own messages and room admins any message
+/// sent to their room.
+impl Handler<DeleteMessage> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<(), SocketError>>;
+
+    fn handle(&mut self, message: DeleteMessage, _: &mut Context<Self>) -> Self::Result {
+        let DeleteMessage { id, message_id } = message;
+        info!("{}{} => {}", "DELETING MESSAGE : ".cyan(), id, message_id);
+        Box::pin(self.find_message(&message_id).map(move |found, act, _| {
+            let error =
+                |code, message| Err(SocketError::new(code, Some("delete_message"), message));
+            let mut message = match found {
+                Some(message) if !message.deleted => message,
+                _ => return error(ErrorCode::NotFound, "No such message"),
+            };
+            let is_room_admin = act
+                .public_rooms
+                .get(&message.receiver_id)
+                .is_some_and(|room| room.is_admin(&id));
+            if message.sender_id != id && !is_room_admin {
+                return error(
+                    ErrorCode::Forbidden,
+                    "You can only delete your own messages",
+                );
+            }
+
+            message.content.clear();
+            message.deleted = true;
+            // Deleted replies don't count towards the reply count stored in the database
+            if let Some(parent_id) = &message.reply_to {
+                act.count_reply(parent_id, -1);
+            }
+            act.db_manager.do_send(SoftDeleteMessage {
+                message_id,
+                deleted_by: id,
+            });
+            act.update_message(message, "message_deleted");
+            Ok(())
+        }))
+    }
+}
+
+/// Adds or removes a reaction to a message of a conversation the user is part of. The new
+/// counts are sent to the conversation with the `reaction` header.
+impl Handler<React> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<(), SocketError>>;
+
+    fn handle(&mut self, message: React, _: &mut Context<Self>) -> Self::Result {
+        let React {
+            id,
+            message_id,
+            emoji,
+            remove,
+        } = message;
+        info!("{}{} => {} {}", "REACTING : ".cyan(), id, message_id, emoji);
+        let error = |code, message| -> Result<(), SocketError> {
+            Err(SocketError::new(code, Some("reaction"), message))
+        };
+        let emoji = emoji.trim().to_string();
+        if emoji.is_empty() || emoji.chars().count() > 8 || emoji.contains(char::is_whitespace) {
+            return Box::pin(fut::ready(error(
+                ErrorCode::SchemaMismatch,
+                "Reactions are a single emoji",
+            )));
+        }
+
+        let lookup = self.find_message(&message_id);
+        let react = move |found: Option<ChatMessage>, act: &mut Self, _: &mut _| -> Self::Result {
+            let message = match found {
+                Some(message) if !message.deleted => message,
+                _ => return Box::pin(fut::ready(error(ErrorCode::NotFound, "No such message"))),
+            };
+            if !act.can_see(&id, &message) {
+                return Box::pin(fut::ready(error(
+                    ErrorCode::Forbidden,
+                    "You can only react to messages of your conversations",
+                )));
+            }
+
+            Box::pin(
+                act.db_manager
+                    .send(StoreReaction {
+                        message_id: message_id.clone(),
+                        user_id: id.clone(),
+                        emoji: emoji.clone(),
+                        remove,
+                    })
+                    .into_actor(act)
+                    .map(move |res, act, _| {
+                        // Nothing changed, the reaction was already there or already gone
+                        let reactions = match res.ok().flatten() {
+                            Some(reactions) => reactions,
+                            None => return Ok(()),
+                        };
+                        let mut message = message;
+                        message.reactions = reactions.clone();
+                        act.cache_update(&message);
+                        let frame = ez_handler::generate_message::<ChatMessage>(
+                            "reaction",
+                            MessageData::Reaction(ReactionUpdate {
+                                message_id,
+                                user_id: id,
+                                emoji,
+                                removed: remove,
+                                reactions,
+                            }),
+                        )
+                        .unwrap();
+                        act.conversation_send(&message, frame);
+                        Ok(())
+                    }),
+            )
+        };
+        Box::pin(lookup.then(react))
+    }
+}
+
+/// Removes the user from the room. If the admin leaves, the admin rights go to another member.
+/// Rooms left without members get archived.
+impl Handler<Leave> for ChatServer {
+    type Result = Result<(), SocketError>;
+
+    fn handle(&mut self, message: Leave, _: &mut Context<Self>) -> Self::Result {
+        let Leave { id, room_id } = message;
+        info!("{}{}{}{}", "LEAVING : ".cyan(), id, " => ".cyan(), room_id);
+
+        match self.public_rooms.get(&room_id) {
+            Some(room) if room.has_user(&id) => {}
+            _ => {
+                return Err(SocketError::new(
+                    ErrorCode::NotFound,
+                    Some("leave"),
+                    "You aren't in that room",
+                ))
+            }
+        }
+
+        // Let the user know they left along with everyone in the room
+        self.room_broadcast(
+            &room_id,
+            ez_handler::generate_message::<RoomData>(
+                "room",
+                MessageData::Room(RoomData::Left((id.clone(), room_id.clone()))),
+            )
+            .unwrap(),
+        );
+
+        let mut new_admin = None;
+        if let Some(room) = self.public_rooms.get_mut(&room_id) {
+            room.remove_user(&id);
+            if room.is_admin(&id) {
+                room.admin = room.users.iter().next().cloned();
+                new_admin = room.admin.clone();
+            }
+        }
+        if let Some(admin_id) = new_admin {
+            self.room_broadcast(
+                &room_id,
+                ez_handler::generate_message::<RoomData>(
+                    "room",
+                    MessageData::Room(RoomData::AdminChanged((room_id.clone(), admin_id.clone()))),
+                )
+                .unwrap(),
+            );
+            self.db_manager.do_send(SetRoomAdmin {
+                room_id: room_id.clone(),
+                admin_id,
+            });
+        }
+
+        self.reset_pointer(&id, &room_id);
+        self.db_manager.do_send(RemoveRoomConnection {
+            room_id: room_id.clone(),
+            user_id: id,
+        });
+        self.archive_if_empty(&room_id);
+        Ok(())
+    }
+}
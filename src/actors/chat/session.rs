@@ -29,6 +29,8 @@ const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 pub struct WsChatSession {
     /// Unique session id obtained from the Authorization cookie
     pub id: String,
+    /// Unique id of this connection, a user can have many open at once
+    pub connection_id: String,
     /// The username of the connected client
     pub username: String,
     /// The currently joined room
@@ -53,6 +55,7 @@ impl WsChatSession {
                 // Notify chat server
                 actor.address.do_send(Disconnect {
                     session_id: actor.id.clone(),
+                    connection_id: actor.connection_id.clone(),
                 });
                 // Stop actor
                 context.stop();
@@ -80,18 +83,22 @@ impl Actor for WsChatSession {
                 username: self.username.clone(),
                 connected: true,
             },
+            connection_id: self.connection_id.clone(),
             address,
         };
         self.address.do_send(message.clone());
         self.rps_address.do_send(message);
     }
 
-    /// Called on actor stop, sends a `Disconnect` message to the server.
+    /// Called on actor stop, sends a `Disconnect` message to the servers.
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
         info!("{}{:?}", "ACTOR STOPPING -- ID : ".red(), self.id);
-        self.address.do_send(Disconnect {
+        let message = Disconnect {
             session_id: self.id.clone(),
-        });
+            connection_id: self.connection_id.clone(),
+        };
+        self.rps_address.do_send(message.clone());
+        self.address.do_send(message);
         Running::Stop
     }
 }
@@ -126,7 +133,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsChatSession {
                 self.heartbeat = Instant::now();
             }
             ws::Message::Text(text) => {
-                ez_handler::handle::<String>(text.to_string(), self, context);
+                ez_handler::handle(text.to_string(), self, context);
             }
             ws::Message::Binary(_) => println!("Unexpected binary"),
             ws::Message::Close(reason) => {
@@ -1,14 +1,33 @@
 use super::messages::*;
+use crate::actors::chat::models::messages::{ChatMessage, MessagePage, ReactionCount};
 use crate::{
+    actors::{chat::models::room::ROOM_MESSAGE_CACHE, rps::tournament::Tournament},
     models::{
-        hall_of_fame::NewHoFEntry, message::NewMessage, room::NewRoom,
-        room_connection::NewRoomConnection,
+        error::GlobalError,
+        game::{Game, NewGame, GAME_CANCELLED, GAME_FINISHED},
+        game_player::{GamePlayer, NewGamePlayer},
+        game_round::NewGameRound,
+        hall_of_fame::NewHoFEntry,
+        message_reaction::MessageReaction,
+        rating::Rating,
+        rating_history::NewRatingChange,
+        message::{Conversation, Message, NewMessage, UnreadCount},
+        room::{NewRoom, Room},
+        room_ban::{NewRoomBan, RoomBan},
+        room_connection::{NewRoomConnection, RoomConnection},
+        season::Season,
+        tournament::{self, NewTournament},
     },
+    services::rating,
     state::db_pool,
 };
 use actix::prelude::*;
 use colored::Colorize;
-use tracing::info;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, PgConnection,
+};
+use tracing::{info, warn};
 
 pub struct DBManager {
     db_pool: db_pool::PgPool,
@@ -18,6 +37,128 @@ impl DBManager {
     pub fn new(db_pool: db_pool::PgPool) -> Self {
         Self { db_pool }
     }
+
+    fn connect(&self) -> Result<PooledConnection<ConnectionManager<PgConnection>>, GlobalError> {
+        self.db_pool.get().map_err(|_| GlobalError::R2D2Error)
+    }
+
+    /// Rebuilds the public rooms with their members and latest messages, along with the
+    /// latest messages overall.
+    fn load_chat_state(&self, message_limit: i64) -> Result<ChatState, GlobalError> {
+        let db_connection = self.connect()?;
+        let connections = RoomConnection::find_all(&db_connection)?;
+        let bans = RoomBan::find_all(&db_connection)?;
+        let mut rooms = vec![];
+        for room in Room::find_active(&db_connection)? {
+            let mut room = room.convert();
+            for connection in &connections {
+                if connection.room_id == room.id {
+                    room.set_user(&connection.user_id);
+                }
+            }
+            for ban in &bans {
+                if ban.room_id == room.id {
+                    room.ban_user(&ban.user_id);
+                }
+            }
+            let conversation = Conversation::Room(room.id.clone());
+            let page = Message::find_page(
+                &db_connection,
+                &conversation,
+                None,
+                ROOM_MESSAGE_CACHE as i64,
+            )?;
+            for message in page.messages {
+                room.store_message(message);
+            }
+            rooms.push(room);
+        }
+        let mut messages: Vec<ChatMessage> = Message::find_recent(&db_connection, message_limit)?
+            .into_iter()
+            .map(Message::convert)
+            .collect();
+        MessageReaction::attach(&db_connection, &mut messages)?;
+        Message::attach_reply_counts(&db_connection, &mut messages)?;
+        Ok(ChatState { rooms, messages })
+    }
+
+    /// Rates the players of a finished game against each other. The winner beats everyone,
+    /// the others are ranked by their score and those who forfeited come last.
+    fn rate_game(&self, game_id: &str, winner: &str) -> Result<(), GlobalError> {
+        let db_connection = self.connect()?;
+        db_connection.transaction::<_, GlobalError, _>(|| {
+            let players = GamePlayer::find_by_games(&db_connection, &[game_id])?;
+            let user_ids: Vec<&str> = players.iter().map(|p| p.user_id.as_str()).collect();
+            let known = Rating::find_by_users(&db_connection, &user_ids)?;
+
+            let mut ratings = vec![];
+            let mut standings = vec![];
+            for player in &players {
+                let rating = known
+                    .iter()
+                    .find(|rating| rating.user_id == player.user_id)
+                    .cloned()
+                    .unwrap_or_else(|| Rating::new(&player.user_id));
+                let standing = if player.user_id == winner {
+                    i32::MAX
+                } else if player.forfeited {
+                    -1
+                } else {
+                    player.score
+                };
+                standings.push((rating.rating, standing));
+                ratings.push(rating);
+            }
+
+            let new_ratings = rating::free_for_all(&standings);
+            let changes: Vec<NewRatingChange> = ratings
+                .iter()
+                .zip(&new_ratings)
+                .map(|(rating, new_rating)| NewRatingChange {
+                    user_id: &rating.user_id,
+                    game_id,
+                    rating_before: rating.rating,
+                    rating_after: *new_rating,
+                })
+                .collect();
+            NewRatingChange::store_all(&db_connection, &changes)?;
+
+            for (mut rating, new_rating) in ratings.into_iter().zip(new_ratings) {
+                rating.rating = new_rating;
+                rating.games_played += 1;
+                if rating.user_id == winner {
+                    rating.wins += 1;
+                }
+                rating.upsert(&db_connection)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn store_game_round(&self, msg: &StoreGameRound) -> Result<(), GlobalError> {
+        let db_connection = self.connect()?;
+        let choices: serde_json::Map<String, serde_json::Value> = msg
+            .choices
+            .iter()
+            .map(|(id, choice)| (id.clone(), choice.clone().into()))
+            .collect();
+        let round = NewGameRound {
+            game_id: &msg.game_id,
+            round: msg.round as i32,
+            choices: choices.into(),
+            excluded: serde_json::to_value(&msg.excluded)?,
+            eliminated: serde_json::to_value(&msg.eliminated)?,
+            winner: msg.winner.as_deref(),
+            commitments: serde_json::to_value(&msg.commitments)?,
+        };
+        db_connection.transaction::<_, GlobalError, _>(|| {
+            round.store(&db_connection)?;
+            if let Some(winner) = &msg.winner {
+                GamePlayer::increment_score(&db_connection, &msg.game_id, winner)?;
+            }
+            Ok(())
+        })
+    }
 }
 
 /// Make actor from `DBManager`
@@ -31,9 +172,11 @@ impl Actor for DBManager {
 impl Handler<StoreChatMessage> for DBManager {
     type Result = ();
     fn handle(&mut self, msg: StoreChatMessage, _: &mut Self::Context) -> Self::Result {
-        let db_connection = self.db_pool.get().unwrap();
-        NewMessage::store(&db_connection, &msg.message, msg.is_room)
-            .expect("Couldn't store message");
+        if let Err(e) = self.connect().and_then(|conn| {
+            NewMessage::store(&conn, &msg.message, msg.is_room).map_err(GlobalError::DieselError)
+        }) {
+            warn!("{}{:?}", "COULDN'T STORE MESSAGE : ".red(), e);
+        }
     }
 }
 
@@ -61,3 +204,355 @@ impl Handler<StoreHoFEntry> for DBManager {
         NewHoFEntry::upsert(&db_connection, &msg.user_id).expect("Couldn't store HoF entry");
     }
 }
+
+impl Handler<LoadChatState> for DBManager {
+    type Result = ChatState;
+    fn handle(&mut self, msg: LoadChatState, _: &mut Self::Context) -> Self::Result {
+        self.load_chat_state(msg.message_limit).unwrap_or_else(|e| {
+            warn!("{}{:?}", "COULDN'T LOAD CHAT STATE : ".red(), e);
+            ChatState::default()
+        })
+    }
+}
+
+impl Handler<LoadHistory> for DBManager {
+    type Result = MessagePage;
+    fn handle(&mut self, msg: LoadHistory, _: &mut Self::Context) -> Self::Result {
+        self.connect()
+            .and_then(|conn| {
+                Message::find_page(&conn, &msg.conversation, msg.before.as_deref(), msg.limit)
+            })
+            .unwrap_or_else(|e| {
+                warn!("{}{:?}", "COULDN'T LOAD HISTORY : ".red(), e);
+                MessagePage::default()
+            })
+    }
+}
+
+impl Handler<MarkRead> for DBManager {
+    type Result = Vec<ChatMessage>;
+    fn handle(&mut self, msg: MarkRead, _: &mut Self::Context) -> Self::Result {
+        match self
+            .connect()
+            .and_then(|conn| Message::mark_read(&conn, &msg.receiver_id, &msg.message_ids))
+        {
+            Ok(messages) => messages.into_iter().map(Message::convert).collect(),
+            Err(e) => {
+                warn!("{}{:?}", "COULDN'T MARK MESSAGES READ : ".red(), e);
+                vec![]
+            }
+        }
+    }
+}
+
+impl Handler<CountUnread> for DBManager {
+    type Result = Vec<UnreadCount>;
+    fn handle(&mut self, msg: CountUnread, _: &mut Self::Context) -> Self::Result {
+        self.connect()
+            .and_then(|conn| Message::count_unread(&conn, &msg.user_id))
+            .unwrap_or_else(|e| {
+                warn!("{}{:?}", "COULDN'T COUNT UNREAD MESSAGES : ".red(), e);
+                vec![]
+            })
+    }
+}
+
+impl Handler<RemoveRoomConnection> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: RemoveRoomConnection, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| RoomConnection::remove(&conn, &msg.room_id, &msg.user_id))
+        {
+            warn!("{}{:?}", "COULDN'T REMOVE ROOM CONNECTION : ".red(), e);
+        }
+    }
+}
+
+impl Handler<StoreRoomBan> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: StoreRoomBan, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| NewRoomBan::store(&conn, &msg.room_id, &msg.user_id))
+        {
+            warn!("{}{:?}", "COULDN'T STORE ROOM BAN : ".red(), e);
+        }
+    }
+}
+
+impl Handler<RenameRoom> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: RenameRoom, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| Room::rename(&conn, &msg.room_id, &msg.name))
+        {
+            warn!("{}{:?}", "COULDN'T RENAME ROOM : ".red(), e);
+        }
+    }
+}
+
+impl Handler<SetRoomAdmin> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: SetRoomAdmin, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| Room::set_admin(&conn, &msg.room_id, &msg.admin_id))
+        {
+            warn!("{}{:?}", "COULDN'T SET ROOM ADMIN : ".red(), e);
+        }
+    }
+}
+
+impl Handler<DeleteRoom> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: DeleteRoom, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| Room::delete(&conn, &msg.room_id))
+        {
+            warn!("{}{:?}", "COULDN'T DELETE ROOM : ".red(), e);
+        }
+    }
+}
+
+impl Handler<ArchiveRoom> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: ArchiveRoom, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| Room::archive(&conn, &msg.room_id))
+        {
+            warn!("{}{:?}", "COULDN'T ARCHIVE ROOM : ".red(), e);
+        }
+    }
+}
+
+impl Handler<LoadMessage> for DBManager {
+    type Result = Option<ChatMessage>;
+    fn handle(&mut self, msg: LoadMessage, _: &mut Self::Context) -> Self::Result {
+        let message = self.connect().and_then(|conn| {
+            let mut message: Vec<ChatMessage> = Message::find_by_id(&conn, &msg.message_id)?
+                .into_iter()
+                .map(Message::convert)
+                .collect();
+            MessageReaction::attach(&conn, &mut message)?;
+            Message::attach_reply_counts(&conn, &mut message)?;
+            Ok(message.pop())
+        });
+        match message {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("{}{:?}", "COULDN'T LOAD MESSAGE : ".red(), e);
+                None
+            }
+        }
+    }
+}
+
+impl Handler<StoreMessageEdit> for DBManager {
+    type Result = bool;
+    fn handle(&mut self, msg: StoreMessageEdit, _: &mut Self::Context) -> Self::Result {
+        let stored = self.connect().and_then(|conn| {
+            Message::edit(
+                &conn,
+                &msg.message_id,
+                &msg.previous,
+                &msg.content,
+                msg.edited_at,
+            )
+        });
+        if let Err(e) = &stored {
+            warn!("{}{:?}", "COULDN'T STORE MESSAGE EDIT : ".red(), e);
+        }
+        stored.is_ok()
+    }
+}
+
+impl Handler<SoftDeleteMessage> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: SoftDeleteMessage, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| Message::soft_delete(&conn, &msg.message_id, &msg.deleted_by))
+        {
+            warn!("{}{:?}", "COULDN'T DELETE MESSAGE : ".red(), e);
+        }
+    }
+}
+
+impl Handler<StoreReaction> for DBManager {
+    type Result = Option<Vec<ReactionCount>>;
+    fn handle(&mut self, msg: StoreReaction, _: &mut Self::Context) -> Self::Result {
+        let reactions = self.connect().and_then(|conn| {
+            let changed = if msg.remove {
+                MessageReaction::remove(&conn, &msg.message_id, &msg.user_id, &msg.emoji)?
+            } else {
+                MessageReaction::add(&conn, &msg.message_id, &msg.user_id, &msg.emoji)?
+            };
+            if changed == 0 {
+                return Ok(None);
+            }
+            let counts = MessageReaction::count(&conn, &[&msg.message_id])?;
+            Ok(Some(
+                counts
+                    .into_iter()
+                    .map(|(_, emoji, count)| ReactionCount { emoji, count })
+                    .collect(),
+            ))
+        });
+        reactions.unwrap_or_else(|e| {
+            warn!("{}{:?}", "COULDN'T STORE REACTION : ".red(), e);
+            None
+        })
+    }
+}
+
+impl Handler<StoreGame> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: StoreGame, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| NewGame::store(&conn, &msg.game))
+        {
+            warn!("{}{:?}", "COULDN'T STORE GAME : ".red(), e);
+        }
+    }
+}
+
+impl Handler<StoreGameRound> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: StoreGameRound, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self.store_game_round(&msg) {
+            warn!("{}{:?}", "COULDN'T STORE GAME ROUND : ".red(), e);
+        }
+    }
+}
+
+impl Handler<StoreGamePlayer> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: StoreGamePlayer, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| NewGamePlayer::store(&conn, &msg.game_id, &msg.user_id))
+        {
+            warn!("{}{:?}", "COULDN'T STORE GAME PLAYER : ".red(), e);
+        }
+    }
+}
+
+impl Handler<StoreForfeit> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: StoreForfeit, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| GamePlayer::forfeit(&conn, &msg.game_id, &msg.user_id))
+        {
+            warn!("{}{:?}", "COULDN'T STORE FORFEIT : ".red(), e);
+        }
+    }
+}
+
+impl Handler<EndGame> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: EndGame, _: &mut Self::Context) -> Self::Result {
+        let status = match msg.winner {
+            Some(_) => GAME_FINISHED,
+            None => GAME_CANCELLED,
+        };
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| Game::end(&conn, &msg.game_id, status, msg.winner.as_deref()))
+        {
+            warn!("{}{:?}", "COULDN'T END GAME : ".red(), e);
+        }
+        if let Some(winner) = &msg.winner {
+            if let Err(e) = self.rate_game(&msg.game_id, winner) {
+                warn!("{}{:?}", "COULDN'T RATE GAME : ".red(), e);
+            }
+        }
+    }
+}
+
+impl Handler<AbandonGames> for DBManager {
+    type Result = ();
+    fn handle(&mut self, _: AbandonGames, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self.connect().and_then(|conn| Game::abandon_running(&conn)) {
+            warn!("{}{:?}", "COULDN'T ABANDON RUNNING GAMES : ".red(), e);
+        }
+    }
+}
+
+impl Handler<LoadCurrentSeason> for DBManager {
+    type Result = Option<i32>;
+    fn handle(&mut self, _: LoadCurrentSeason, _: &mut Self::Context) -> Self::Result {
+        match self.connect().and_then(|conn| Season::find_current(&conn)) {
+            Ok(season) => season.map(|season| season.id),
+            Err(e) => {
+                warn!("{}{:?}", "COULDN'T LOAD CURRENT SEASON : ".red(), e);
+                None
+            }
+        }
+    }
+}
+
+impl Handler<LoadRating> for DBManager {
+    type Result = f64;
+    fn handle(&mut self, msg: LoadRating, _: &mut Self::Context) -> Self::Result {
+        match self
+            .connect()
+            .and_then(|conn| Rating::find_by_users(&conn, &[&msg.user_id]))
+        {
+            Ok(ratings) => ratings
+                .first()
+                .map_or(rating::DEFAULT_RATING, |rating| rating.rating),
+            Err(e) => {
+                warn!("{}{:?}", "COULDN'T LOAD RATING : ".red(), e);
+                rating::DEFAULT_RATING
+            }
+        }
+    }
+}
+
+impl Handler<LoadRatings> for DBManager {
+    type Result = Vec<Rating>;
+    fn handle(&mut self, msg: LoadRatings, _: &mut Self::Context) -> Self::Result {
+        let user_ids: Vec<&str> = msg.user_ids.iter().map(String::as_str).collect();
+        match self
+            .connect()
+            .and_then(|conn| Rating::find_by_users(&conn, &user_ids))
+        {
+            Ok(ratings) => ratings,
+            Err(e) => {
+                warn!("{}{:?}", "COULDN'T LOAD RATINGS : ".red(), e);
+                vec![]
+            }
+        }
+    }
+}
+
+impl Handler<LoadTournaments> for DBManager {
+    type Result = Vec<Tournament>;
+    fn handle(&mut self, _: LoadTournaments, _: &mut Self::Context) -> Self::Result {
+        match self.connect().and_then(|conn| tournament::find_unfinished(&conn)) {
+            Ok(tournaments) => tournaments,
+            Err(e) => {
+                warn!("{}{:?}", "COULDN'T LOAD TOURNAMENTS : ".red(), e);
+                vec![]
+            }
+        }
+    }
+}
+
+impl Handler<StoreTournament> for DBManager {
+    type Result = ();
+    fn handle(&mut self, msg: StoreTournament, _: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self
+            .connect()
+            .and_then(|conn| NewTournament::store(&conn, &msg.tournament))
+        {
+            warn!("{}{:?}", "COULDN'T STORE TOURNAMENT : ".red(), e);
+        }
+    }
+}
\ No newline at end of file
@@ -1,8 +1,23 @@
 use crate::{
-    actors::chat::models::{messages::ChatMessage, room::PublicRoom},
+    actors::{
+        chat::models::{
+            messages::{ChatMessage, MessagePage, ReactionCount},
+            room::PublicRoom,
+        },
+        rps::{
+            game::{Commitment, RPS},
+            tournament::Tournament,
+        },
+    },
+    models::{
+        message::{Conversation, UnreadCount},
+        rating::Rating,
+    },
 };
-use actix::Message;
+use actix::{Message, MessageResponse};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Message, Debug, Serialize, Deserialize)]
 #[rtype(result = "()")]
@@ -30,3 +45,200 @@ pub struct StoreRoomConnection {
     pub room_id: String,
     pub user_id: String,
 }
+
+/// Loads everything the chat server needs to pick up where it left off.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "ChatState")]
+pub struct LoadChatState {
+    /// How many of the latest messages to load into the chat server's cache
+    pub message_limit: i64,
+}
+
+/// The persisted state of the chat server. Rooms contain their members and latest messages.
+#[derive(MessageResponse, Debug, Default, Serialize, Deserialize)]
+pub struct ChatState {
+    pub rooms: Vec<PublicRoom>,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Loads a page of up to `limit` messages of a conversation sent before the message
+/// with the ID `before`.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "MessagePage")]
+pub struct LoadHistory {
+    pub conversation: Conversation,
+    pub before: Option<String>,
+    pub limit: i64,
+}
+
+/// Marks the private messages with the given IDs read, if `receiver_id` received them.
+/// Returns the messages that weren't read before.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Vec<ChatMessage>")]
+pub struct MarkRead {
+    pub receiver_id: String,
+    pub message_ids: Vec<String>,
+}
+
+/// Counts the unread private messages of the user per sender.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Vec<UnreadCount>")]
+pub struct CountUnread {
+    pub user_id: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct RemoveRoomConnection {
+    pub room_id: String,
+    pub user_id: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct StoreRoomBan {
+    pub room_id: String,
+    pub user_id: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct RenameRoom {
+    pub room_id: String,
+    pub name: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct SetRoomAdmin {
+    pub room_id: String,
+    pub admin_id: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct DeleteRoom {
+    pub room_id: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct ArchiveRoom {
+    pub room_id: String,
+}
+
+/// Loads a single chat message, `None` if there's no such message.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Option<ChatMessage>")]
+pub struct LoadMessage {
+    pub message_id: String,
+}
+
+/// Replaces the content of the message, storing the previous content as a revision.
+/// Returns false if the edit couldn't be stored.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "bool")]
+pub struct StoreMessageEdit {
+    pub message_id: String,
+    pub previous: String,
+    pub content: String,
+    pub edited_at: DateTime<Utc>,
+}
+
+/// Soft deletes the message.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct SoftDeleteMessage {
+    pub message_id: String,
+    pub deleted_by: String,
+}
+
+/// Adds or removes a user's reaction. Returns the message's reactions after the change, `None`
+/// if nothing changed.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Option<Vec<ReactionCount>>")]
+pub struct StoreReaction {
+    pub message_id: String,
+    pub user_id: String,
+    pub emoji: String,
+    pub remove: bool,
+}
+
+/// Stores a newly registered game along with its players.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct StoreGame {
+    pub game: RPS,
+}
+
+/// Stores a resolved round and increments the round winner's score.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct StoreGameRound {
+    pub game_id: String,
+    pub round: usize,
+    pub choices: Vec<(String, String)>,
+    pub excluded: HashSet<String>,
+    pub eliminated: HashSet<String>,
+    pub winner: Option<String>,
+    pub commitments: HashMap<String, Commitment>,
+}
+
+/// Adds a player who accepted their invitation to the game.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct StoreGamePlayer {
+    pub game_id: String,
+    pub user_id: String,
+}
+
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct StoreForfeit {
+    pub game_id: String,
+    pub user_id: String,
+}
+
+/// Finishes the game, it's cancelled if there's no winner.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct EndGame {
+    pub game_id: String,
+    pub winner: Option<String>,
+}
+
+/// Loads the ID of the season that's currently running.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Option<i32>")]
+pub struct LoadCurrentSeason;
+
+/// Loads the rating of the user, the default rating if they haven't played yet.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "f64")]
+pub struct LoadRating {
+    pub user_id: String,
+}
+
+/// Marks the games left running by a previous run of the server as abandoned.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct AbandonGames;
+
+/// Loads the ratings of the users that played at least one rated game.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Vec<Rating>")]
+pub struct LoadRatings {
+    pub user_ids: Vec<String>,
+}
+
+/// Loads the tournaments left registering or running by a previous run of the server.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "Vec<Tournament>")]
+pub struct LoadTournaments;
+
+/// Stores the current state of the tournament and its players.
+#[derive(Message, Debug, Serialize, Deserialize)]
+#[rtype(result = "()")]
+pub struct StoreTournament {
+    pub tournament: Tournament,
+}
@@ -1,2 +1,2 @@
 pub mod manager;
-pub mod messages;
\ No newline at end of file
+pub mod messages;
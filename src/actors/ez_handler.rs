@@ -1,109 +1,231 @@
 //! Every text message the `WsChatSession` stream handler receives is sent to this
 //! handler for processing.
-use super::chat::models::messages::{ChatMessage, CreateRoom, Join, Read};
+use super::chat::models::messages::{ChatMessage, Read};
 
 use super::chat::session::WsChatSession;
-use crate::actors::models::messages::client_message::{MessageData, ClientMessage};
+use crate::actors::models::messages::{
+    client_message::{ClientMessage, Inbound, MessageData},
+    error::{ErrorCode, SocketError},
+};
 use crate::actors::rps::{game::RPS, models::RPSData};
 use crate::models::error::GlobalError;
 use actix::prelude::*;
 use actix_web_actors::ws::WebsocketContext;
 use colored::Colorize;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::Serialize;
 use serde_json::Value;
 use tracing::log::{info, warn};
 
-/// Parses the text message to a `ClientMessage` struct and sends the appropriate message to
-/// the server.
-pub fn handle<T>(
+/// Parses the text message to an `Inbound` message and sends the appropriate message to
+/// the server. Messages that can't be parsed are answered with an `error` message instead.
+pub fn handle(
     text: String,
     session: &mut WsChatSession,
     context: &mut WebsocketContext<WsChatSession>,
-) where
-    T: Serialize + DeserializeOwned,
-{
-    let header = get_header(text.clone());
-    info!("{}{:?}", "GOT HEADER : ".yellow(), header);
-    match header.as_ref() {
-        "chat_message" => {
-            let message = parse_message::<ChatMessage>(text);
-            if let MessageData::ChatMessage(chat_message) = message.data.clone() {
-                let client_message = ClientMessage::<ChatMessage> {
-                    header: message.header.clone(),
-                    data: MessageData::ChatMessage(chat_message),
-                };
-                session.address.do_send(client_message);
-            }
+) {
+    let mut message = match parse_message(&text) {
+        Ok(message) => message,
+        Err(e) => {
+            warn!("{}{}", "BAD MESSAGE : ".red(), e);
+            send_error(context, e);
+            return;
         }
-        "join" => {
-            let message = parse_message::<Join>(text);
-            if let MessageData::Join(message) = message.data {
-                session
-                    .address
-                    .send(Join {
-                        id: message.id,
-                        room_id: message.room_id,
-                    })
-                    .into_actor(session)
-                    .then(|res, _, ctx| {
-                        match res {
-                            Ok(messages) => {
-                                info!("SENDING MESSAGES EZ : {:?}", messages);
-                                ctx.text(
-                                    generate_message("messages", MessageData::List(messages))
-                                        .unwrap(),
-                                );
-                            }
-                            Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
-                        }
-                        fut::ready(())
-                    })
-                    .wait(context)
-            }
+    };
+    if let Err(e) = authenticate(&mut message, session) {
+        send_error(context, e);
+        return;
+    }
+    info!("{}{:?}", "GOT MESSAGE : ".yellow(), message);
+    match message {
+        Inbound::ChatMessage { data } => {
+            let client_message = ClientMessage::<ChatMessage> {
+                header: String::from("chat_message"),
+                data: MessageData::ChatMessage(data),
+            };
+            session.address.do_send(client_message);
         }
-        "read" => {
-            let message = parse_message::<ChatMessage>(text);
-            if let MessageData::List::<ChatMessage>(messages) = message.data {
-                session.address.do_send(Read { messages })
-            }
+        Inbound::Join { data } => session
+            .address
+            .send(data)
+            .into_actor(session)
+            .then(|res, _, ctx| {
+                match res {
+                    Ok(Ok(messages)) => {
+                        info!("SENDING MESSAGES EZ : {:?}", messages);
+                        ctx.text(
+                            generate_message("messages", MessageData::List(messages)).unwrap(),
+                        );
+                    }
+                    Ok(Err(e)) => send_error(ctx, e),
+                    Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
+                }
+                fut::ready(())
+            })
+            .wait(context),
+        Inbound::Read { data } => session.address.do_send(Read {
+            id: session.id.clone(),
+            messages: data,
+        }),
+        Inbound::History { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(|res, _, ctx| {
+                    match res {
+                        Ok(Ok(page)) => ctx.text(
+                            generate_message::<ChatMessage>("history", MessageData::Page(page))
+                                .unwrap(),
+                        ),
+                        Ok(Err(e)) => send_error(ctx, e),
+                        Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
+                    }
+                    fut::ready(())
+                })
+                .wait(context)
         }
-        "room" => {
-            let message = parse_message::<CreateRoom>(text);
-            if let MessageData::CreateRoom(CreateRoom { sender_id, name }) = message.data {
-                session.address.do_send(CreateRoom { sender_id, name })
-            }
+        Inbound::Thread { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(|res, _, ctx| {
+                    match res {
+                        Ok(Ok(page)) => ctx.text(
+                            generate_message::<ChatMessage>("thread", MessageData::Page(page))
+                                .unwrap(),
+                        ),
+                        Ok(Err(e)) => send_error(ctx, e),
+                        Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
+                    }
+                    fut::ready(())
+                })
+                .wait(context)
         }
-        "rps" => {
-            let message = parse_message::<RPSData>(text);
-            info!("{}{:?}", "GOT RPS MESSAGE : ".purple(), message);
-            if let MessageData::RPS(msg) = message.data {
-                session
-                    .rps_address
-                    .send(msg)
-                    .into_actor(session)
-                    .then(|res, _, ctx| {
-                        match res {
-                            Ok(rps_data) => match rps_data {
-                                RPSData::None => {}
-                                _ => {
-                                    ctx.text(
-                                        generate_message::<RPS>("rps", MessageData::RPS(rps_data))
-                                            .unwrap(),
-                                    );
-                                }
-                            },
-                            Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
-                        }
-                        fut::ready(())
-                    })
-                    .wait(context)
-            }
+        Inbound::Room { data } => session.address.do_send(data),
+        Inbound::RoomAdmin { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(report_error)
+                .wait(context)
+        }
+        Inbound::Leave { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(report_error)
+                .wait(context)
+        }
+        Inbound::EditMessage { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(report_error)
+                .wait(context)
         }
-        // Obligatory sanity lol
-        "lol" => context.text(
+        Inbound::DeleteMessage { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(report_error)
+                .wait(context)
+        }
+        Inbound::Reaction { mut data } => {
+            data.id = session.id.clone();
+            session
+                .address
+                .send(data)
+                .into_actor(session)
+                .then(report_error)
+                .wait(context)
+        }
+        Inbound::Rps { data } => session
+            .rps_address
+            .send(*data)
+            .into_actor(session)
+            .then(|res, _, ctx| {
+                match res {
+                    Ok(rps_data) => match rps_data {
+                        RPSData::None => {}
+                        RPSData::Error(e) => send_error(ctx, e),
+                        _ => {
+                            ctx.text(
+                                generate_message::<RPS>("rps", MessageData::RPS(Box::new(rps_data))).unwrap(),
+                            );
+                        }
+                    },
+                    Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
+                }
+                fut::ready(())
+            })
+            .wait(context),
+        Inbound::Lol => context.text(
             generate_message::<String>("lel", MessageData::String(String::from("lel"))).unwrap(),
         ),
-        _ => warn!("Bad message"),
+    }
+}
+
+/// Checks the sender the message claims against the session's user, whose ID comes from the
+/// verified JWT. Omitted senders are filled in, anyone else is rejected.
+fn authenticate(message: &mut Inbound, session: &WsChatSession) -> Result<(), SocketError> {
+    let (header, claimed) = match message {
+        Inbound::ChatMessage { data } => ("chat_message", &mut data.sender_id),
+        Inbound::Join { data } => ("join", &mut data.id),
+        Inbound::Room { data } => ("room", &mut data.sender_id),
+        Inbound::Rps { data } => match data.sender_mut() {
+            Some(sender_id) => ("rps", sender_id),
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    if !claimed.is_empty() && *claimed != session.id {
+        warn!(
+            "{}{} CLAIMED TO BE {} IN `{}`",
+            "SECURITY -- IDENTITY MISMATCH : ".red(),
+            session.id,
+            claimed,
+            header
+        );
+        return Err(SocketError::new(
+            ErrorCode::IdentityMismatch,
+            Some(header),
+            "The sender doesn't match the logged in user",
+        ));
+    }
+    *claimed = session.id.clone();
+    Ok(())
+}
+
+/// Sends an `error` message if the server rejected a message that expects no other response.
+fn report_error(
+    res: Result<Result<(), SocketError>, MailboxError>,
+    _: &mut WsChatSession,
+    ctx: &mut WebsocketContext<WsChatSession>,
+) -> fut::Ready<()> {
+    match res {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => send_error(ctx, e),
+        Err(e) => warn!("SOMETHING WENT WRONG : {:?}", e),
+    }
+    fut::ready(())
+}
+
+/// Sends an `error` message describing why the client's message was rejected.
+pub fn send_error(context: &mut WebsocketContext<WsChatSession>, error: SocketError) {
+    match generate_message::<String>("error", MessageData::Error(error)) {
+        Ok(message) => context.text(message),
+        Err(e) => warn!("Couldn't serialize error message : {:?}", e),
     }
 }
 
@@ -120,16 +242,42 @@ where
     .map_err(|e| GlobalError::SerdeError(e))
 }
 
-/// Parses text to `ClientMessage`
-#[inline]
-pub fn parse_message<T: DeserializeOwned + Serialize>(message: String) -> ClientMessage<T> {
-    info!("GOT MESSAGE PARSING : {}", message);
-    serde_json::from_str::<ClientMessage<T>>(&message.trim()).unwrap()
+/// Parses text to an `Inbound` message. The header is checked separately from the data so
+/// the client knows whether it sent something unknown or something malformed.
+pub fn parse_message(message: &str) -> Result<Inbound, SocketError> {
+    let value: Value = serde_json::from_str(message.trim())
+        .map_err(|e| SocketError::new(ErrorCode::InvalidJson, None, e))?;
+    let header = match value.get("header").and_then(Value::as_str) {
+        Some(header) => header.to_string(),
+        None => {
+            return Err(SocketError::new(
+                ErrorCode::MissingHeader,
+                None,
+                "Message has no header",
+            ))
+        }
+    };
+    serde_json::from_value(value).map_err(|e| {
+        // Serde lists every known header when the tag doesn't match any `Inbound` variant
+        let code = if e.to_string().starts_with("unknown variant") {
+            ErrorCode::UnknownHeader
+        } else {
+            ErrorCode::SchemaMismatch
+        };
+        SocketError::new(code, Some(&header), e)
+    })
 }
 
-#[inline]
-pub fn get_header<'a>(s: String) -> String {
-    let message: Value = serde_json::from_str(&s).unwrap();
-    let header = &message["header"];
-    header.as_str().expect("Couldn't parse header").to_string()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_headers_are_told_apart_from_bad_data() {
+        let code = |text| parse_message(text).unwrap_err().code;
+        assert!(matches!(code(r#"{"header":"nope"}"#), ErrorCode::UnknownHeader));
+        assert!(matches!(code(r#"{"header":"join"}"#), ErrorCode::SchemaMismatch));
+        assert!(matches!(code(r#"{"data":{}}"#), ErrorCode::MissingHeader));
+        assert!(matches!(parse_message(r#"{"header":"lol"}"#), Ok(Inbound::Lol)));
+    }
 }
@@ -1,10 +1,14 @@
 use actix::Message;
 use serde::{Deserialize, Serialize};
 
+use super::error::SocketError;
 use crate::actors::{
     chat::models::{
         chat_user::ChatUser,
-        messages::{ChatMessage, CreateRoom, Join},
+        messages::{
+            ChatMessage, CreateRoom, DeleteMessage, EditMessage, History, Join, Leave, MessagePage,
+            React, ReactionUpdate, RoomAdmin, Thread,
+        },
         room::RoomData,
     },
     rps::models::RPSData,
@@ -38,10 +42,61 @@ where
     ChatMessage(ChatMessage),
     Join(Join),
     /// Contains all data related to RPS games.
-    RPS(RPSData),
+    RPS(Box<RPSData>),
     /// Contains all data related to rooms.
     Room(RoomData),
     CreateRoom(CreateRoom),
+    /// A page of a conversation's history
+    Page(MessagePage),
+    /// Sent with the `reaction` header when a message's reactions change
+    Reaction(ReactionUpdate),
+    /// Sent with the `error` header when a client message couldn't be processed.
+    Error(SocketError),
+}
+
+/// Every message a client can send, tagged by its header. The data of each message
+/// is kept under the `data` key, same as in `ClientMessage`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "header", rename_all = "snake_case")]
+pub enum Inbound {
+    ChatMessage {
+        data: ChatMessage,
+    },
+    Join {
+        data: Join,
+    },
+    Read {
+        data: Vec<ChatMessage>,
+    },
+    History {
+        data: History,
+    },
+    Room {
+        data: CreateRoom,
+    },
+    RoomAdmin {
+        data: RoomAdmin,
+    },
+    Leave {
+        data: Leave,
+    },
+    EditMessage {
+        data: EditMessage,
+    },
+    DeleteMessage {
+        data: DeleteMessage,
+    },
+    Reaction {
+        data: React,
+    },
+    Thread {
+        data: Thread,
+    },
+    Rps {
+        data: Box<RPSData>,
+    },
+    /// Obligatory sanity
+    Lol,
 }
 
 /// Shortcuts for serializing messages to JSON.
@@ -9,13 +9,17 @@ use super::client_message::SocketMessage;
 #[rtype(result = "()")]
 pub struct Connect {
     pub user: ChatUser,
+    /// Identifies this connection among all the connections the user has open
+    pub connection_id: String,
     pub address: Recipient<SocketMessage>,
 }
 
 /// Removes the corresponding session from the actor's session store
-#[derive(Message, Debug)]
+#[derive(Message, Debug, Clone)]
 #[rtype(result = "()")]
 pub struct Disconnect {
     /// The ID of the session to disconnect
     pub session_id: String,
+    /// The ID of the connection that closed
+    pub connection_id: String,
 }
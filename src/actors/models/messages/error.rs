@@ -0,0 +1,57 @@
+//! Errors reported back to the client through the socket.
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Indicates what went wrong with a message the client sent.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The frame isn't valid JSON
+    InvalidJson,
+    /// The frame is JSON, but has no `header` string
+    MissingHeader,
+    /// The header isn't registered in the `ez_handler`
+    UnknownHeader,
+    /// The header is known, but the data doesn't match what it expects
+    SchemaMismatch,
+    /// The password of a protected room is missing or wrong
+    WrongPassword,
+    /// The user isn't allowed to do that
+    Forbidden,
+    /// The thing the message refers to doesn't exist
+    NotFound,
+    /// The move isn't part of the game's rule set
+    InvalidMove,
+    /// The game variant can't be played
+    InvalidRules,
+    /// The revealed move doesn't match the player's commitment
+    CommitmentMismatch,
+    /// The message claims to come from a different user than the logged in one
+    IdentityMismatch,
+}
+
+/// Sent to the client with the `error` header whenever one of its messages can't be processed.
+/// The session stays alive, so the client can just correct itself and try again.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SocketError {
+    pub code: ErrorCode,
+    /// The header of the offending message, if it could be read
+    pub header: Option<String>,
+    pub message: String,
+}
+
+impl SocketError {
+    pub fn new(code: ErrorCode, header: Option<&str>, message: impl ToString) -> Self {
+        Self {
+            code,
+            header: header.map(|h| h.to_string()),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for SocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} ({:?}): {}", self.code, self.header, self.message)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod client_message;
 pub mod connection;
+pub mod error;
\ No newline at end of file
@@ -1 +1,2 @@
-pub mod messages;
\ No newline at end of file
+pub mod messages;
+pub mod sessions;
\ No newline at end of file
@@ -0,0 +1,77 @@
+//! Keeps track of every connection each user has open.
+use super::messages::client_message::SocketMessage;
+use actix::Recipient;
+use std::collections::HashMap;
+
+/// Maps user IDs to the addresses of all their open connections. Each connection (e.g. a browser
+/// tab) has its own ID, so a user only goes offline once their last connection closes.
+#[derive(Default)]
+pub struct Sessions {
+    users: HashMap<String, HashMap<String, Recipient<SocketMessage>>>,
+}
+
+impl Sessions {
+    /// Registers the connection. Returns true if it's the user's first open connection.
+    pub fn insert(
+        &mut self,
+        user_id: &str,
+        connection_id: &str,
+        address: Recipient<SocketMessage>,
+    ) -> bool {
+        let connections = self.users.entry(user_id.to_string()).or_default();
+        connections.insert(connection_id.to_string(), address);
+        connections.len() == 1
+    }
+
+    /// Removes the connection. Returns true if it was the user's last open connection.
+    pub fn remove(&mut self, user_id: &str, connection_id: &str) -> bool {
+        let connections = match self.users.get_mut(user_id) {
+            Some(connections) => connections,
+            None => return false,
+        };
+        if connections.remove(connection_id).is_none() {
+            return false;
+        }
+        if connections.is_empty() {
+            self.users.remove(user_id);
+            return true;
+        }
+        false
+    }
+
+    pub fn is_connected(&self, user_id: &str) -> bool {
+        self.users.contains_key(user_id)
+    }
+
+    /// Returns the IDs of all connected users
+    pub fn user_ids(&self) -> impl Iterator<Item = &String> {
+        self.users.keys()
+    }
+
+    /// Sends the message to every connection of the user.
+    pub fn send(&self, user_id: &str, message: &str) {
+        if let Some(connections) = self.users.get(user_id) {
+            for address in connections.values() {
+                address.do_send(SocketMessage(message.to_string()));
+            }
+        }
+    }
+
+    /// Sends the message to a single connection of the user.
+    pub fn send_to(&self, user_id: &str, connection_id: &str, message: &str) {
+        if let Some(address) = self
+            .users
+            .get(user_id)
+            .and_then(|connections| connections.get(connection_id))
+        {
+            address.do_send(SocketMessage(message.to_string()));
+        }
+    }
+
+    /// Sends the message to every open connection.
+    pub fn broadcast(&self, message: &str) {
+        for address in self.users.values().flat_map(|connections| connections.values()) {
+            address.do_send(SocketMessage(message.to_string()));
+        }
+    }
+}
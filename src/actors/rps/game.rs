@@ -1,11 +1,15 @@
+use super::models::TimeoutPenalty;
+use super::rules::RuleSet;
 use actix::prelude::*;
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use rand;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::collections::HashSet;
-use tracing::{info, warn};
+use tracing::info;
 
 const EPIC_WORDS: &[&str] = &[
     "Deadly Dispute",
@@ -21,18 +25,47 @@ pub struct RPS {
     pub name: String,
     pub host: String,
     pub player_ids: HashSet<String>,
-    pub choices: HashMap<String, char>,
+    /// Players that were invited but haven't answered yet
+    pub invited: HashSet<String>,
+    pub choices: HashMap<String, String>,
     pub scores: HashMap<String, usize>,
     pub connections: HashSet<String>,
+    /// Users watching the game without playing
+    pub spectators: HashSet<String>,
     pub fast_mode: bool,
     pub locked: bool,
     pub excluded: HashSet<String>,
     pub game_over: bool,
     pub gg_score: usize,
+    pub timeout_penalty: TimeoutPenalty,
+    pub rules: RuleSet,
+    /// Players commit to a hash of their move before revealing it
+    pub commit_reveal: bool,
+    /// Maps the players to the hash they committed to this round
+    pub commitments: HashMap<String, String>,
+    /// Maps the players to the nonce of their verified reveal, kept private until the round
+    /// resolves since it gives the move away
+    #[serde(skip)]
+    pub reveals: HashMap<String, String>,
+    /// The season the game is played in
+    pub season_id: Option<i32>,
+    /// The tournament the game is a match of
+    pub tournament_id: Option<String>,
+    /// The number of rounds resolved so far
+    pub round: usize,
+    /// When the current round times out
+    pub deadline: Option<DateTime<Utc>>,
 }
 
 impl RPS {
-    pub fn new(players: Vec<String>, host: String, id: &str, gg_score: usize) -> Self {
+    pub fn new(
+        players: Vec<String>,
+        host: String,
+        id: &str,
+        gg_score: usize,
+        timeout_penalty: TimeoutPenalty,
+        rules: RuleSet,
+    ) -> Self {
         let name = generate_epic_word();
         let choices = HashMap::new();
         let excluded = HashSet::new();
@@ -49,28 +82,117 @@ impl RPS {
             name,
             host,
             player_ids,
+            invited: HashSet::new(),
             scores,
             connections,
+            spectators: HashSet::new(),
             choices,
             fast_mode: false,
             locked: false,
             excluded,
             game_over: false,
             gg_score,
+            timeout_penalty,
+            rules,
+            commit_reveal: false,
+            commitments: HashMap::new(),
+            reveals: HashMap::new(),
+            season_id: None,
+            tournament_id: None,
+            round: 0,
+            deadline: None,
         }
     }
 
+    /// Returns true if the user is playing, invited to or watching the game
+    pub fn is_visible_to(&self, user_id: &str) -> bool {
+        self.player_ids.contains(user_id)
+            || self.invited.contains(user_id)
+            || self.spectators.contains(user_id)
+    }
+
+    /// Returns the state sent to spectators, without the choices of the round being played
+    pub fn spectator_view(&self) -> Self {
+        Self {
+            choices: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Adds an invited player to the game. Returns false if they weren't invited.
+    pub fn accept_invite(&mut self, player_id: &str) -> bool {
+        if !self.invited.remove(player_id) {
+            return false;
+        }
+        self.spectators.remove(player_id);
+        self.player_ids.insert(player_id.to_string());
+        self.scores.insert(player_id.to_string(), 0);
+        true
+    }
+
     pub fn toggle_fast(&mut self, flag: bool) {
         self.fast_mode = flag;
     }
 
-    pub fn choose_rps(&mut self, rps: char, player_id: String) -> Option<RpsResolve> {
-        if self.excluded.contains(&player_id) {
+    /// Maps the player to the move, which has to be one of the game's rule set
+    pub fn choose_rps(&mut self, rps: String, player_id: String) -> Option<RpsResolve> {
+        if self.excluded.contains(&player_id) || !self.connections.contains(&player_id) {
             return None;
         }
-        let choice = self.choices.entry(player_id).or_insert(rps);
-        *choice = rps;
-        if self.choices.values().len() == self.connections.len() - self.excluded.len() {
+        self.choices.insert(player_id, rps);
+        self.try_resolve()
+    }
+
+    /// Stores the player's commitment for the round. Returns false if they aren't playing the
+    /// round or already committed.
+    pub fn commit(&mut self, player_id: &str, hash: String) -> bool {
+        if !self.connections.contains(player_id)
+            || self.excluded.contains(player_id)
+            || self.commitments.contains_key(player_id)
+        {
+            return false;
+        }
+        self.commitments.insert(player_id.to_string(), hash);
+        true
+    }
+
+    /// Returns true once every connected player that isn't excluded committed
+    pub fn all_committed(&self) -> bool {
+        self.connections
+            .iter()
+            .filter(|id| !self.excluded.contains(*id))
+            .all(|id| self.commitments.contains_key(id))
+    }
+
+    /// Checks the revealed move and nonce against the player's commitment
+    pub fn verify_reveal(&self, player_id: &str, choice: &str, nonce: &str) -> bool {
+        self.commitments
+            .get(player_id)
+            .is_some_and(|hash| hash.eq_ignore_ascii_case(&commitment_hash(choice, nonce)))
+    }
+
+    /// Clears the commitments of the round, returning them along with their verified nonces
+    pub fn take_commitments(&mut self) -> HashMap<String, Commitment> {
+        let reveals = std::mem::take(&mut self.reveals);
+        self.commitments
+            .drain()
+            .map(|(id, hash)| {
+                let nonce = reveals.get(&id).cloned();
+                (id, Commitment { hash, nonce })
+            })
+            .collect()
+    }
+
+    /// Resolves the round if every connected player that isn't excluded made their choice.
+    /// A player left alone while the others are reconnecting can't play the round by themselves.
+    pub fn try_resolve(&mut self) -> Option<RpsResolve> {
+        let mut active = self
+            .connections
+            .iter()
+            .filter(|id| !self.excluded.contains(*id))
+            .peekable();
+        let all_chose = active.peek().is_some() && active.all(|id| self.choices.contains_key(id));
+        if self.connections.len() >= 2 && all_chose {
             let winners = self.resolve_rps();
             Some(winners)
         } else {
@@ -86,44 +208,11 @@ impl RPS {
             if !self.excluded.contains(&conn_id) {
                 let mut score: i8 = 0;
                 let player_choice = self.choices.get(&conn_id).unwrap();
-                for (_id, choice) in self.choices.clone() {
-                    match player_choice {
-                        'r' => {
-                            if choice == 's' {
-                                score += 1;
-                                continue;
-                            }
-                            if choice == 'p' {
-                                score -= 1;
-                            }
-                        }
-                        'p' => {
-                            if choice == 'r' {
-                                score += 1;
-                                continue;
-                            }
-                            if choice == 's' {
-                                score -= 1;
-                            }
-                        }
-                        's' => {
-                            if choice == 'p' {
-                                score += 1;
-                                continue;
-                            }
-                            if choice == 'r' {
-                                score -= 1;
-                            }
-                        }
-                        'x' => {
-                            if choice == 'x' {
-                                continue;
-                            }
-                            score += 1;
-                        }
-                        _ => {
-                            warn!("{}", "Got invalid RPS key".red())
-                        }
+                for choice in self.choices.values() {
+                    if self.rules.beats(player_choice, choice) {
+                        score += 1;
+                    } else if self.rules.beats(choice, player_choice) {
+                        score -= 1;
                     }
                 }
                 results.insert(conn_id, score);
@@ -167,6 +256,44 @@ impl RPS {
         RpsResolve::Winner(winner)
     }
 
+    /// Returns the connected players that are still in the round but haven't made their choice.
+    /// While a commit-reveal game waits for commitments, only the players who didn't commit
+    /// are idle since the others can't reveal yet.
+    pub fn idle_players(&self) -> HashSet<String> {
+        let committing = self.commit_reveal && !self.all_committed();
+        self.connections
+            .iter()
+            .filter(|id| !self.excluded.contains(*id))
+            .filter(|id| {
+                if committing {
+                    !self.commitments.contains_key(*id)
+                } else {
+                    !self.choices.contains_key(*id)
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the penalty for the idle players of the round. Commit-reveal games exclude them
+    /// instead of picking for them, otherwise players who see they're losing could skip their
+    /// reveal to get a fresh random move.
+    pub fn round_penalty(&self) -> TimeoutPenalty {
+        match self.timeout_penalty {
+            TimeoutPenalty::RandomPick if self.commit_reveal => TimeoutPenalty::Exclude,
+            penalty => penalty,
+        }
+    }
+
+    /// Excludes the players from the current round
+    pub fn exclude(&mut self, player_ids: &HashSet<String>) {
+        self.excluded.extend(player_ids.iter().cloned());
+        // No one is left to play the round, let everyone back in
+        if self.excluded.len() >= self.connections.len() {
+            self.reset_excluded();
+        }
+    }
+
     pub fn reset_choices(&mut self) {
         self.choices.clear();
     }
@@ -175,24 +302,118 @@ impl RPS {
         self.excluded.clear();
     }
 
-    fn _disconnect_player(&mut self, player_id: &str) {
+    /// Drops the player from the current round, they stay in the game and can rejoin
+    pub fn disconnect_player(&mut self, player_id: &str) {
         self.choices.remove(player_id);
+        self.commitments.remove(player_id);
+        self.reveals.remove(player_id);
         self.connections.remove(player_id);
+        self.excluded.remove(player_id);
+    }
+
+    /// Removes the player from the game for good
+    pub fn forfeit_player(&mut self, player_id: &str) {
+        self.disconnect_player(player_id);
+        self.player_ids.remove(player_id);
+        if self.host == player_id {
+            if let Some(id) = self.player_ids.iter().next() {
+                self.host = id.clone();
+            }
+        }
     }
 
     pub fn end(&mut self) {
         self.game_over = true;
+        self.deadline = None;
     }
 }
 
+/// Returns the hex encoded SHA-256 hash of `move:nonce`, the value players commit to
+pub fn commitment_hash(choice: &str, nonce: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("{}:{}", choice, nonce)))
+}
+
 fn generate_epic_word() -> String {
     let mut rng = rand::thread_rng();
     let idx = rng.gen_range(0..EPIC_WORDS.len());
     EPIC_WORDS[idx].to_string()
 }
 
+/// A player's commitment for a round, stored so the game can be audited.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Commitment {
+    pub hash: String,
+    /// Missing if the player never revealed their move
+    pub nonce: Option<String>,
+}
+
 /// Internal type returned by the `resolve_rps()` function
 pub enum RpsResolve {
     Exclude(HashSet<String>),
     Winner(String)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game with both players connected
+    fn two_player_game(commit_reveal: bool) -> RPS {
+        let mut game = RPS::new(
+            vec!["p1".into(), "p2".into()],
+            "p1".into(),
+            "g",
+            3,
+            TimeoutPenalty::RandomPick,
+            RuleSet::default(),
+        );
+        game.connections.insert("p2".into());
+        game.commit_reveal = commit_reveal;
+        game
+    }
+
+    #[test]
+    fn rounds_need_two_connected_players() {
+        let mut game = two_player_game(false);
+        game.disconnect_player("p2");
+        assert!(game.choose_rps("rock".into(), "p1".into()).is_none());
+
+        game.connections.insert("p2".into());
+        assert!(game.choose_rps("paper".into(), "p2".into()).is_some());
+    }
+
+    #[test]
+    fn reveals_have_to_match_the_commitment() {
+        let mut game = two_player_game(true);
+        assert!(game.commit("p1", commitment_hash("rock", "salt")));
+        // Commitments can't be changed once made
+        assert!(!game.commit("p1", commitment_hash("paper", "salt")));
+        assert!(!game.all_committed());
+
+        assert!(game.verify_reveal("p1", "rock", "salt"));
+        assert!(!game.verify_reveal("p1", "paper", "salt"));
+        assert!(!game.verify_reveal("p1", "rock", "pepper"));
+        assert!(!game.verify_reveal("p2", "rock", "salt"));
+    }
+
+    #[test]
+    fn players_who_dont_reveal_are_excluded_instead_of_picked_for() {
+        let mut game = two_player_game(true);
+        assert_eq!(game.round_penalty(), TimeoutPenalty::Exclude);
+
+        // Players who committed aren't idle while others are still committing
+        game.commit("p1", commitment_hash("rock", "salt"));
+        assert_eq!(game.idle_players(), HashSet::from(["p2".to_string()]));
+
+        // Once revealing starts, committed players who don't reveal are idle
+        game.commit("p2", commitment_hash("scissors", "salt"));
+        assert!(game.choose_rps("rock".into(), "p1".into()).is_none());
+        let idle = game.idle_players();
+        assert_eq!(idle, HashSet::from(["p2".to_string()]));
+
+        game.exclude(&idle);
+        match game.try_resolve() {
+            Some(RpsResolve::Winner(winner)) => assert_eq!(winner, "p1"),
+            _ => panic!("the player who revealed should win the round"),
+        }
+    }
+}
@@ -1,120 +1,975 @@
-use super::models::{Event, RPSData, Update};
+use super::models::{
+    Event, Queue, RPSData, SetSeason, TimeoutPenalty, TournamentAction, TournamentRequest, Update,
+};
+use super::queue::{MatchmakingQueue, QueueEntry};
 use crate::actors::{
-    db::{manager::DBManager, messages::StoreHoFEntry},
+    db::{
+        manager::DBManager,
+        messages::{
+            AbandonGames, EndGame, LoadCurrentSeason, LoadRating, LoadRatings, LoadTournaments,
+            StoreForfeit, StoreGame, StoreGamePlayer, StoreGameRound, StoreHoFEntry,
+            StoreTournament,
+        },
+    },
     ez_handler,
     models::messages::{
-        client_message::{MessageData, SocketMessage},
-        connection::Connect,
+        client_message::MessageData,
+        connection::{Connect, Disconnect},
+        error::{ErrorCode, SocketError},
     },
+    models::sessions::Sessions,
 };
+use crate::services::rating::DEFAULT_RATING;
 use actix::prelude::*;
 use actix::Actor;
+use chrono::Utc;
 use colored::Colorize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use tracing::info;
 use tracing::log::warn;
 use uuid::Uuid;
 
 use super::game::{RpsResolve, RPS};
 use super::models::RPSAction;
+use super::rules::RuleSet;
+use super::tournament::{Tournament, TournamentStatus};
+
+/// How long a disconnected player has to rejoin before forfeiting
+const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+/// How long invited players have to answer
+const INVITE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long the players of a game set up by the server have to join it before forfeiting
+const JOIN_TIMEOUT: Duration = Duration::from_secs(60);
+/// The most players a game found by matchmaking can have
+const MAX_QUEUE_PLAYERS: usize = 8;
+/// How long players have to make their choice each round
+const ROUND_TIME: Duration = Duration::from_secs(20);
+/// Round time when fast mode is on
+const FAST_ROUND_TIME: Duration = Duration::from_secs(5);
 
 /// An actor that maintains the state of all RPS games
 pub struct RPSManager {
-    sessions: HashMap<String, Recipient<SocketMessage>>,
+    sessions: Sessions,
     games: HashMap<String, RPS>,
+    /// Maps game and player IDs to the grace period timer of a disconnected player
+    grace_periods: HashMap<(String, String), SpawnHandle>,
+    /// Maps game IDs to the timer of their current round
+    round_timers: HashMap<String, SpawnHandle>,
+    /// The season new games are played in
+    season_id: Option<i32>,
+    /// Players waiting to be matched into a game
+    queue: MatchmakingQueue,
+    tournaments: HashMap<String, Tournament>,
+    /// Maps the IDs of the games played as tournament matches to their tournament
+    tournament_games: HashMap<String, String>,
     db_manager: Addr<DBManager>,
 }
 
 impl RPSManager {
     pub fn new(db_manager: Addr<DBManager>) -> Self {
         Self {
-            sessions: HashMap::new(),
+            sessions: Sessions::default(),
             games: HashMap::new(),
+            grace_periods: HashMap::new(),
+            round_timers: HashMap::new(),
+            season_id: None,
+            queue: MatchmakingQueue::default(),
+            tournaments: HashMap::new(),
+            tournament_games: HashMap::new(),
             db_manager,
         }
     }
 
-    /// Register a new RPS game with the given players and the given player id as the host
-    pub fn register_game(&mut self, players: Vec<String>, host: String, gg_score: usize) -> RPS {
+    /// Register a new RPS game with the given players and the given player id as the host.
+    /// Players that don't join it in time forfeit.
+    pub fn register_game(
+        &mut self,
+        players: Vec<String>,
+        host: String,
+        gg_score: usize,
+        timeout_penalty: TimeoutPenalty,
+        rules: RuleSet,
+        ctx: &mut Context<Self>,
+    ) -> RPS {
         let id = Uuid::new_v4().to_string();
-        self.games
-            .insert(id.clone(), RPS::new(players, host, &id, gg_score));
+        let mut game = RPS::new(players, host, &id, gg_score, timeout_penalty, rules);
+        // No one is connected until they join, the host might not even be online
+        game.connections.clear();
+        let game = self.start_game(game, ctx);
+        self.expect_players(&game.id, ctx);
+        game
+    }
+
+    /// Tags the game with the current season, starts its first round, stores and broadcasts it
+    fn start_game(&mut self, mut game: RPS, ctx: &mut Context<Self>) -> RPS {
+        let id = game.id.clone();
+        game.season_id = self.season_id;
+        self.games.insert(id.clone(), game);
         info!("{}{:?}", "ACTIVE GAMES : ".purple(), self.games);
+        self.start_round(&id, ctx);
         let game = self.games.get(&id).unwrap().clone();
+        self.db_manager.do_send(StoreGame { game: game.clone() });
         self.broadcast(&game);
         game
     }
 
-    /// Returns all registered games
-    fn get_games(&self) -> Vec<RPS> {
-        self.games.values().cloned().collect()
+    /// Registers a game for every group of compatible players in the queue and sends it
+    /// to them. The player that waited the longest hosts the game.
+    fn match_players(&mut self, ctx: &mut Context<Self>) {
+        while let Some(group) = self.queue.find_match() {
+            let host = group[0].user_id.clone();
+            let gg_score = group[0].gg_score;
+            let players: Vec<String> = group.into_iter().map(|entry| entry.user_id).collect();
+            info!("{}{:?}", "MATCHED PLAYERS : ".purple(), players);
+
+            let game = self.register_game(
+                players.clone(),
+                host,
+                gg_score,
+                TimeoutPenalty::default(),
+                RuleSet::default(),
+                ctx,
+            );
+            let message = ez_handler::generate_message::<RPS>(
+                "rps",
+                MessageData::RPS(Box::new(RPSData::Matched(game))),
+            )
+            .unwrap();
+            for player_id in players {
+                self.sessions.send(&player_id, &message);
+            }
+        }
+    }
+
+    /// Forfeits the players of the game that haven't joined it once `JOIN_TIMEOUT` runs out
+    fn expect_players(&mut self, game_id: &str, ctx: &mut Context<Self>) {
+        let id = game_id.to_string();
+        ctx.run_later(JOIN_TIMEOUT, move |act, ctx| act.forfeit_no_shows(&id, ctx));
     }
 
-    /// Directly send a message to the address of the given session ID.
-    fn send_direct(&self, receiver: &str, message: String) {
-        if let Some(address) = self.sessions.get(receiver) {
-            let _ = address.do_send(SocketMessage(message));
+    /// Forfeits the players that never joined the game. Players that joined and dropped
+    /// already have their grace period running. The game is cancelled if no one showed up.
+    fn forfeit_no_shows(&mut self, game_id: &str, ctx: &mut Context<Self>) {
+        let game = match self.games.get(game_id) {
+            Some(game) if !game.game_over => game,
+            _ => return,
+        };
+        let mut no_shows: Vec<String> = game
+            .player_ids
+            .iter()
+            .filter(|id| {
+                !game.connections.contains(*id)
+                    && !self
+                        .grace_periods
+                        .contains_key(&(game_id.to_string(), id.to_string()))
+            })
+            .cloned()
+            .collect();
+        if no_shows.is_empty() {
+            return;
+        }
+        info!("{}{:?}", "PLAYERS DIDN'T SHOW UP : ".red(), no_shows);
+        let last = no_shows.pop().unwrap();
+        // Only the last one can end the game, otherwise a no-show could win it
+        for player_id in no_shows {
+            self.remove_player(game_id, &player_id);
+        }
+        self.forfeit(game_id, &last, ctx);
+    }
+
+    /// Invites the players to the game and sends them its state. The invitations expire
+    /// after `INVITE_TIMEOUT`, the game is cancelled if no one accepted by then.
+    fn invite_players(&mut self, game_id: &str, player_ids: Vec<String>, ctx: &mut Context<Self>) {
+        let game = self.games.get_mut(game_id).unwrap();
+        let invited: Vec<String> = player_ids
+            .into_iter()
+            .filter(|id| !game.player_ids.contains(id))
+            .collect();
+        game.invited.extend(invited.iter().cloned());
+
+        let game = self.games.get(game_id).unwrap();
+        self.broadcast(game);
+        let invite = ez_handler::generate_message::<RPS>(
+            "rps",
+            MessageData::RPS(Box::new(RPSData::Update(Update::new(
+                game_id,
+                Event::Invite(game.host.clone()),
+            )))),
+        )
+        .unwrap();
+        for player_id in &invited {
+            self.sessions.send(player_id, &invite);
+        }
+
+        let id = game_id.to_string();
+        ctx.run_later(INVITE_TIMEOUT, move |act, ctx| act.expire_invites(&id, ctx));
+    }
+
+    /// Drops the invitations that weren't answered and tells the host about them
+    fn expire_invites(&mut self, game_id: &str, ctx: &mut Context<Self>) {
+        let game = match self.games.get_mut(game_id) {
+            Some(game) if !game.game_over => game,
+            _ => return,
+        };
+        let expired: Vec<String> = game.invited.drain().collect();
+        let game = self.games.get(game_id).unwrap();
+        for player_id in expired {
+            self.send_host(game, Event::InviteExpired(player_id));
+        }
+
+        // No one accepted, the host can't play alone
+        if game.player_ids.len() < 2 {
+            let host = game.host.clone();
+            self.forfeit(game_id, &host, ctx);
         }
     }
 
-    /// Broadcasts a message to all sessions connected to the RPS manager.
+    /// Sends an update to all connections of the game's host
+    fn send_host(&self, game: &RPS, event: Event) {
+        let mut data = Update::new(&game.id, event);
+        data.deadline = game.deadline;
+        self.sessions.send(
+            &game.host,
+            &ez_handler::generate_message::<RPS>(
+                "rps",
+                MessageData::RPS(Box::new(RPSData::Update(data))),
+            )
+            .unwrap(),
+        );
+    }
+
+    /// Returns all registered games the user can see
+    fn get_games(&self, user_id: &str) -> Vec<RPS> {
+        self.games
+            .values()
+            .filter(|game| game.is_visible_to(user_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Sends the game state to every connected user that can see the game. Spectators
+    /// don't see the choices of the round being played.
     pub fn broadcast(&self, rps: &RPS) {
-        info!("BROADCASTING TO : {:?}", self.sessions.keys());
-        for (_, address) in &self.sessions {
-            address.do_send(SocketMessage(
-                ez_handler::generate_message::<RPS>(
-                    "rps",
-                    MessageData::RPS(RPSData::State(rps.clone())),
-                )
-                .unwrap(),
-            ));
+        let message = ez_handler::generate_message::<RPS>(
+            "rps",
+            MessageData::RPS(Box::new(RPSData::State(rps.clone()))),
+        )
+        .unwrap();
+        let spectator_message = ez_handler::generate_message::<RPS>(
+            "rps",
+            MessageData::RPS(Box::new(RPSData::State(rps.spectator_view()))),
+        )
+        .unwrap();
+        for id in self.sessions.user_ids() {
+            if rps.spectators.contains(id) {
+                self.sessions.send(id, &spectator_message);
+            } else if rps.is_visible_to(id) {
+                self.sessions.send(id, &message);
+            }
+        }
+    }
+
+    /// Broadcasts the outcome of a round, ending the game if the winner reached the score threshold
+    fn resolve_round(&mut self, game_id: &str, resolve: RpsResolve, ctx: &mut Context<Self>) {
+        let game = self.games.get_mut(game_id).unwrap();
+        // Drain choices from game
+        let choices: Vec<(String, String)> = game.choices.drain().collect();
+        game.round += 1;
+
+        // Store the round, the losers were already added to the excluded players
+        let (eliminated, winner) = match &resolve {
+            RpsResolve::Exclude(losers) => (losers.clone(), None),
+            RpsResolve::Winner(winner) => (HashSet::new(), Some(winner.clone())),
+        };
+        self.db_manager.do_send(StoreGameRound {
+            game_id: game_id.to_string(),
+            round: game.round,
+            choices: choices.clone(),
+            excluded: game.excluded.difference(&eliminated).cloned().collect(),
+            eliminated,
+            winner,
+            commitments: game.take_commitments(),
+        });
+
+        match resolve {
+            RpsResolve::Exclude(losers) => {
+                // Shadow the mutable reference
+                let game = self.games.get(game_id).unwrap();
+                // Broadcast choices
+                self.room_broadcast(
+                    game,
+                    RPSData::Update(Update::new(&game.id, Event::Choices(choices))),
+                );
+                // Broadcast losers
+                self.room_broadcast(
+                    game,
+                    RPSData::Update(Update::new(&game.id, Event::Exclude(losers))),
+                );
+                self.start_round(game_id, ctx);
+            }
+            RpsResolve::Winner(winner) => {
+                game.reset_excluded();
+                // Shadow the mutable reference
+                let game = self.games.get(game_id).unwrap();
+                // Broadcast choices
+                self.room_broadcast(
+                    game,
+                    RPSData::Update(Update::new(&game.id, Event::Choices(choices))),
+                );
+                // Broadcast winner
+                self.room_broadcast(
+                    game,
+                    RPSData::Update(Update::new(&game.id, Event::Winner(winner.clone()))),
+                );
+
+                // If the score threshold is reached
+                if game.scores.get(&winner).unwrap() >= &game.gg_score {
+                    info!("MAXIMUM SCORE REACHED -- WINNER : {:?}", winner);
+                    self.end_game(game_id, winner, ctx);
+                } else {
+                    self.start_round(game_id, ctx);
+                }
+            }
+        }
+    }
+
+    /// Ends the game, stores the winner in the hall of fame and notifies the players
+    fn end_game(&mut self, game_id: &str, winner: String, ctx: &mut Context<Self>) {
+        self.stop_round(game_id, ctx);
+        let game = self.games.get_mut(game_id).unwrap();
+        game.end();
+
+        self.db_manager.do_send(EndGame {
+            game_id: game_id.to_string(),
+            winner: Some(winner.clone()),
+        });
+        self.db_manager.do_send(StoreHoFEntry {
+            user_id: winner.clone(),
+        });
+
+        let game = self.games.get(game_id).unwrap();
+        self.room_broadcast(
+            game,
+            RPSData::Update(Update::new(&game.id, Event::GG(game.id.clone()))),
+        );
+        self.advance_tournament(game_id, Some(winner), ctx);
+    }
+
+    /// Starts a new round timer for the game, replacing the running one. Players that didn't
+    /// make their choice when it runs out get the game's `TimeoutPenalty`. The timer only
+    /// runs once at least two players joined.
+    fn start_round(&mut self, game_id: &str, ctx: &mut Context<Self>) {
+        self.stop_round(game_id, ctx);
+        let game = match self.games.get_mut(game_id) {
+            Some(game) if !game.game_over => game,
+            _ => return,
+        };
+        if game.connections.len() < 2 {
+            game.deadline = None;
+            return;
+        }
+        let round_time = if game.fast_mode {
+            FAST_ROUND_TIME
+        } else {
+            ROUND_TIME
+        };
+        game.deadline = Some(Utc::now() + chrono::Duration::from_std(round_time).unwrap());
+
+        let id = game_id.to_string();
+        let handle = ctx.run_later(round_time, move |act, ctx| {
+            act.round_timers.remove(&id);
+            act.round_timeout(&id, ctx);
+        });
+        self.round_timers.insert(game_id.to_string(), handle);
+
+        let game = self.games.get(game_id).unwrap();
+        self.room_broadcast(
+            game,
+            RPSData::Update(Update::new(game_id, Event::RoundStarted)),
+        );
+    }
+
+    /// Cancels the round timer of the game
+    fn stop_round(&mut self, game_id: &str, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.round_timers.remove(game_id) {
+            ctx.cancel_future(handle);
+        }
+    }
+
+    /// Applies the game's `TimeoutPenalty` to the players that didn't make their choice in time
+    fn round_timeout(&mut self, game_id: &str, ctx: &mut Context<Self>) {
+        let game = match self.games.get_mut(game_id) {
+            Some(game) if !game.game_over => game,
+            _ => return,
+        };
+        let idle = game.idle_players();
+        if !idle.is_empty() {
+            info!("{}{:?}", "ROUND TIMED OUT -- IDLE : ".yellow(), idle);
+            let penalty = game.round_penalty();
+            let game = self.games.get(game_id).unwrap();
+            self.room_broadcast(
+                game,
+                RPSData::Update(Update::new(game_id, Event::TimedOut(idle.clone()))),
+            );
+
+            match penalty {
+                TimeoutPenalty::RandomPick => {
+                    let game = self.games.get_mut(game_id).unwrap();
+                    for player_id in idle {
+                        let choice = game.rules.random_move();
+                        game.choices.insert(player_id, choice);
+                    }
+                    if let Some(resolve) = game.try_resolve() {
+                        self.resolve_round(game_id, resolve, ctx);
+                    }
+                }
+                TimeoutPenalty::Exclude => {
+                    let game = self.games.get_mut(game_id).unwrap();
+                    let committing = game.commit_reveal && !game.all_committed();
+                    game.exclude(&idle);
+                    let resolve = game.try_resolve();
+                    // Excluding the players who didn't commit lets the others reveal
+                    let reveal = committing && game.all_committed();
+
+                    let game = self.games.get(game_id).unwrap();
+                    self.room_broadcast(
+                        game,
+                        RPSData::Update(Update::new(game_id, Event::Exclude(idle))),
+                    );
+                    if reveal {
+                        self.room_broadcast(
+                            game,
+                            RPSData::Update(Update::new(game_id, Event::RevealStarted)),
+                        );
+                    }
+                    if let Some(resolve) = resolve {
+                        self.resolve_round(game_id, resolve, ctx);
+                    }
+                }
+                TimeoutPenalty::Forfeit => {
+                    for player_id in idle {
+                        self.forfeit(game_id, &player_id, ctx);
+                    }
+                }
+            }
+        }
+
+        // Nothing got resolved, give the players another round
+        if !self.round_timers.contains_key(game_id) {
+            self.start_round(game_id, ctx);
         }
     }
 
-    pub fn room_broadcast(&self, game: &RPS, data: RPSData) {
-        for (id, address) in &self.sessions {
-            if game.connections.contains(id) {
-                address.do_send(SocketMessage(
-                    ez_handler::generate_message::<RPS>("rps", MessageData::RPS(data.clone()))
-                        .unwrap(),
+    /// Drops the player from every running game they are connected to and gives them
+    /// `RECONNECT_GRACE` to rejoin before they forfeit.
+    fn disconnect_player(&mut self, player_id: &str, ctx: &mut Context<Self>) {
+        let watched: Vec<String> = self
+            .games
+            .values_mut()
+            .filter_map(|game| game.spectators.remove(player_id).then(|| game.id.clone()))
+            .collect();
+        for game_id in watched {
+            let game = self.games.get(&game_id).unwrap();
+            self.room_broadcast(
+                game,
+                RPSData::Update(Update::new(
+                    &game_id,
+                    Event::SpectatorLeft(player_id.to_string()),
+                )),
+            );
+        }
+
+        let game_ids: Vec<String> = self
+            .games
+            .values()
+            .filter(|game| !game.game_over && game.connections.contains(player_id))
+            .map(|game| game.id.clone())
+            .collect();
+
+        for game_id in game_ids {
+            let game = self.games.get_mut(&game_id).unwrap();
+            game.disconnect_player(player_id);
+            let resolve = game.try_resolve();
+
+            let game = self.games.get(&game_id).unwrap();
+            self.room_broadcast(
+                game,
+                RPSData::Update(Update::new(
+                    &game_id,
+                    Event::PlayerDisconnected(player_id.to_string()),
+                )),
+            );
+
+            // The remaining players might have all made their choice already
+            if let Some(resolve) = resolve {
+                self.resolve_round(&game_id, resolve, ctx);
+            }
+
+            let (id, player) = (game_id.clone(), player_id.to_string());
+            let handle = ctx.run_later(RECONNECT_GRACE, move |act, ctx| {
+                act.grace_periods.remove(&(id.clone(), player.clone()));
+                act.forfeit(&id, &player, ctx);
+            });
+            self.grace_periods
+                .insert((game_id, player_id.to_string()), handle);
+        }
+    }
+
+    /// Removes a player who didn't rejoin in time. The last player standing wins, and the
+    /// game is cancelled if no one is left.
+    fn forfeit(&mut self, game_id: &str, player_id: &str, ctx: &mut Context<Self>) {
+        match self.games.get(game_id) {
+            Some(game) if !game.game_over => {}
+            _ => return,
+        }
+        let resolve = self.remove_player(game_id, player_id);
+
+        let game = self.games.get(game_id).unwrap();
+        match game.player_ids.len() {
+            0 => {
+                info!("{}{:?}", "GAME CANCELLED : ".red(), game_id);
+                self.stop_round(game_id, ctx);
+                self.db_manager.do_send(EndGame {
+                    game_id: game_id.to_string(),
+                    winner: None,
+                });
+                let mut game = self.games.remove(game_id).unwrap();
+                game.end();
+                self.room_broadcast(
+                    &game,
+                    RPSData::Update(Update::new(game_id, Event::Cancelled(game_id.to_string()))),
+                );
+                self.broadcast(&game);
+                self.advance_tournament(game_id, None, ctx);
+            }
+            1 => {
+                let winner = game.player_ids.iter().next().unwrap().clone();
+                info!("WON BY FORFEIT -- WINNER : {:?}", winner);
+                self.end_game(game_id, winner, ctx);
+            }
+            _ => {
+                if let Some(resolve) = resolve {
+                    self.resolve_round(game_id, resolve, ctx);
+                }
+            }
+        }
+    }
+
+    /// Removes the player from the game for good and lets everyone know. Returns the
+    /// outcome of the round if the remaining players all made their choice.
+    fn remove_player(&mut self, game_id: &str, player_id: &str) -> Option<RpsResolve> {
+        info!("{}{:?}", "PLAYER FORFEITED : ".red(), player_id);
+        let game = self.games.get_mut(game_id).unwrap();
+        game.forfeit_player(player_id);
+        let resolve = game.try_resolve();
+        self.db_manager.do_send(StoreForfeit {
+            game_id: game_id.to_string(),
+            user_id: player_id.to_string(),
+        });
+
+        let game = self.games.get(game_id).unwrap();
+        self.room_broadcast(
+            game,
+            RPSData::Update(Update::new(game_id, Event::Forfeit(player_id.to_string()))),
+        );
+        resolve
+    }
+
+    /// Returns all tournaments the user can see
+    fn get_tournaments(&self, user_id: &str) -> Vec<Tournament> {
+        self.tournaments
+            .values()
+            .filter(|tournament| tournament.is_visible_to(user_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Sends the tournament to every connected user that can see it, or to everyone so
+    /// they learn it no longer takes registrations
+    fn broadcast_tournament(&self, tournament: &Tournament, everyone: bool) {
+        let message = ez_handler::generate_message::<RPS>(
+            "rps",
+            MessageData::RPS(Box::new(RPSData::Bracket(tournament.clone()))),
+        )
+        .unwrap();
+        for id in self.sessions.user_ids() {
+            if everyone || tournament.is_visible_to(id) {
+                self.sessions.send(id, &message);
+            }
+        }
+    }
+
+    fn tournament_action(&mut self, msg: TournamentRequest, ctx: &mut Context<Self>) -> RPSData {
+        let tournament_id = match msg.action {
+            TournamentAction::Create {
+                name,
+                format,
+                best_of,
+                variant,
+            } => {
+                if let Err(e) = variant.rules() {
+                    return RPSData::Error(SocketError::new(
+                        ErrorCode::InvalidRules,
+                        Some("rps"),
+                        e,
+                    ));
+                }
+                let id = Uuid::new_v4().to_string();
+                let mut tournament =
+                    Tournament::new(&id, name, msg.sender_id.clone(), format, best_of, variant);
+                tournament.register(&msg.sender_id);
+                self.db_manager.do_send(StoreTournament {
+                    tournament: tournament.clone(),
+                });
+                self.broadcast_tournament(&tournament, true);
+                self.tournaments.insert(id, tournament);
+                return RPSData::None;
+            }
+            TournamentAction::Register(ref id)
+            | TournamentAction::Withdraw(ref id)
+            | TournamentAction::Start(ref id) => id.clone(),
+        };
+
+        let tournament = match self.tournaments.get_mut(&tournament_id) {
+            Some(tournament) => tournament,
+            None => {
+                return RPSData::Error(SocketError::new(
+                    ErrorCode::NotFound,
+                    Some("rps"),
+                    format!("There is no tournament with the ID {}", tournament_id),
                 ))
             }
+        };
+        if tournament.status != TournamentStatus::Registering {
+            return RPSData::Error(SocketError::new(
+                ErrorCode::Forbidden,
+                Some("rps"),
+                format!("The tournament {} already started", tournament.name),
+            ));
+        }
+
+        match msg.action {
+            TournamentAction::Start(_) => {
+                if tournament.host != msg.sender_id {
+                    return RPSData::Error(SocketError::new(
+                        ErrorCode::Forbidden,
+                        Some("rps"),
+                        "Only the host can start the tournament".to_string(),
+                    ));
+                }
+                if tournament.entrants.len() < 2 {
+                    return RPSData::Error(SocketError::new(
+                        ErrorCode::Forbidden,
+                        Some("rps"),
+                        "The tournament needs at least two players".to_string(),
+                    ));
+                }
+                let user_ids = tournament
+                    .entrants
+                    .iter()
+                    .map(|entrant| entrant.user_id.clone())
+                    .collect();
+                // Wait for the ratings so no one registers while the players are seeded
+                self.db_manager
+                    .send(LoadRatings { user_ids })
+                    .into_actor(self)
+                    .map(move |res, act, ctx| {
+                        let ratings = res
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|rating| (rating.user_id, rating.rating))
+                            .collect();
+                        let tournament = act.tournaments.get_mut(&tournament_id).unwrap();
+                        let matches = tournament.start(&ratings);
+                        info!("{}{:?}", "TOURNAMENT STARTED : ".purple(), tournament);
+
+                        let tournament = act.tournaments.get(&tournament_id).unwrap();
+                        act.broadcast_tournament(tournament, true);
+                        act.spawn_matches(&tournament_id, matches, ctx);
+                    })
+                    .wait(ctx);
+            }
+            action => {
+                let changed = match action {
+                    TournamentAction::Register(_) => tournament.register(&msg.sender_id),
+                    _ => tournament.unregister(&msg.sender_id),
+                };
+                if changed {
+                    let tournament = self.tournaments.get(&tournament_id).unwrap();
+                    self.db_manager.do_send(StoreTournament {
+                        tournament: tournament.clone(),
+                    });
+                    self.broadcast_tournament(tournament, true);
+                }
+            }
+        }
+        RPSData::None
+    }
+
+    /// Registers a game for each of the tournament's matches and sends it to its players,
+    /// then stores and broadcasts the bracket. Players that don't join their match in time
+    /// forfeit it.
+    fn spawn_matches(&mut self, tournament_id: &str, matches: Vec<usize>, ctx: &mut Context<Self>) {
+        let tournament = self.tournaments.get(tournament_id).unwrap();
+        let gg_score = tournament.gg_score();
+        let rules = tournament.variant.rules().unwrap_or_default();
+        let pairings: Vec<(usize, Vec<String>)> = matches
+            .into_iter()
+            .map(|i| (i, tournament.matches[i].players.clone()))
+            .collect();
+
+        for (i, players) in pairings {
+            let mut game = RPS::new(
+                players.clone(),
+                players[0].clone(),
+                &Uuid::new_v4().to_string(),
+                gg_score,
+                TimeoutPenalty::default(),
+                rules.clone(),
+            );
+            game.tournament_id = Some(tournament_id.to_string());
+            // No one is connected until they join, the host might not even be online
+            game.connections.clear();
+            let game = self.start_game(game, ctx);
+            self.expect_players(&game.id, ctx);
+            self.tournament_games
+                .insert(game.id.clone(), tournament_id.to_string());
+            let tournament = self.tournaments.get_mut(tournament_id).unwrap();
+            tournament.matches[i].game_id = Some(game.id.clone());
+
+            let message = ez_handler::generate_message::<RPS>(
+                "rps",
+                MessageData::RPS(Box::new(RPSData::Matched(game))),
+            )
+            .unwrap();
+            for player_id in players {
+                self.sessions.send(&player_id, &message);
+            }
+        }
+
+        let tournament = self.tournaments.get(tournament_id).unwrap();
+        self.db_manager.do_send(StoreTournament {
+            tournament: tournament.clone(),
+        });
+        self.broadcast_tournament(tournament, false);
+    }
+
+    /// Records the result of a tournament match and spawns the next round once the current
+    /// one is over. The champion is credited in the hall of fame when the tournament ends.
+    fn advance_tournament(
+        &mut self,
+        game_id: &str,
+        winner: Option<String>,
+        ctx: &mut Context<Self>,
+    ) {
+        let tournament_id = match self.tournament_games.remove(game_id) {
+            Some(tournament_id) => tournament_id,
+            None => return,
+        };
+        let tournament = match self.tournaments.get_mut(&tournament_id) {
+            Some(tournament) => tournament,
+            None => return,
+        };
+        let matches = if tournament.record_result(game_id, winner) {
+            tournament.advance()
+        } else {
+            vec![]
+        };
+
+        if tournament.status == TournamentStatus::Finished {
+            let tournament = self.tournaments.remove(&tournament_id).unwrap();
+            info!("TOURNAMENT OVER -- CHAMPION : {:?}", tournament.champion);
+            if let Some(champion) = &tournament.champion {
+                self.db_manager.do_send(StoreHoFEntry {
+                    user_id: champion.clone(),
+                });
+            }
+            self.db_manager.do_send(StoreTournament {
+                tournament: tournament.clone(),
+            });
+            self.broadcast_tournament(&tournament, false);
+            return;
+        }
+        self.spawn_matches(&tournament_id, matches, ctx);
+    }
+
+    /// Picks the tournaments back up after a restart. Their games didn't survive it, so the
+    /// open matches of the running ones are played again from the start.
+    fn restore_tournaments(&mut self, tournaments: Vec<Tournament>, ctx: &mut Context<Self>) {
+        for tournament in tournaments {
+            info!("{}{:?}", "RESTORING TOURNAMENT : ".purple(), tournament.id);
+            let id = tournament.id.clone();
+            let running = tournament.status == TournamentStatus::Running;
+            let matches = tournament.playable();
+            self.tournaments.insert(id.clone(), tournament);
+            if running {
+                self.spawn_matches(&id, matches, ctx);
+            }
+        }
+    }
+
+    /// Sends the data to the connected players of the game and its spectators
+    pub fn room_broadcast(&self, game: &RPS, mut data: RPSData) {
+        if let RPSData::Update(update) = &mut data {
+            update.deadline = game.deadline;
+        }
+        let message =
+            ez_handler::generate_message::<RPS>("rps", MessageData::RPS(Box::new(data))).unwrap();
+        for id in self.sessions.user_ids() {
+            if game.connections.contains(id) || game.spectators.contains(id) {
+                self.sessions.send(id, &message);
+            }
         }
     }
 }
 
 impl Actor for RPSManager {
     type Context = Context<Self>;
-    fn started(&mut self, _ctx: &mut Context<Self>) {
+    fn started(&mut self, ctx: &mut Context<Self>) {
         info!("{}", "Started RPS Manager".green());
+        // Games don't survive restarts, close the ones left running
+        self.db_manager.do_send(AbandonGames);
+        self.db_manager
+            .send(LoadCurrentSeason)
+            .into_actor(self)
+            .map(|res, act, _| match res {
+                Ok(season_id) => act.season_id = season_id,
+                Err(e) => warn!("{}{:?}", "COULDN'T LOAD CURRENT SEASON : ".red(), e),
+            })
+            .wait(ctx);
+        // Restore the tournaments once the season is known, their matches are played in it
+        self.db_manager
+            .send(LoadTournaments)
+            .into_actor(self)
+            .map(|res, act, ctx| match res {
+                Ok(tournaments) => act.restore_tournaments(tournaments, ctx),
+                Err(e) => warn!("{}{:?}", "COULDN'T LOAD TOURNAMENTS : ".red(), e),
+            })
+            .wait(ctx);
     }
 }
 
 impl Handler<Connect> for RPSManager {
     type Result = ();
     fn handle(&mut self, msg: Connect, _: &mut Self::Context) -> Self::Result {
-        self.sessions.insert(msg.user.id.clone(), msg.address);
-        // Send all active games to the user
-        self.send_direct(
+        self.sessions
+            .insert(&msg.user.id, &msg.connection_id, msg.address);
+        // Send all active games to the new connection
+        self.sessions.send_to(
+            &msg.user.id,
+            &msg.connection_id,
+            &ez_handler::generate_message::<RPS>(
+                "rps",
+                MessageData::RPS(Box::new(RPSData::Rooms(self.get_games(&msg.user.id)))),
+            )
+            .unwrap(),
+        );
+        self.sessions.send_to(
             &msg.user.id,
-            ez_handler::generate_message::<RPS>(
+            &msg.connection_id,
+            &ez_handler::generate_message::<RPS>(
                 "rps",
-                MessageData::RPS(RPSData::Rooms(self.get_games())),
+                MessageData::RPS(Box::new(RPSData::Tournaments(
+                    self.get_tournaments(&msg.user.id),
+                ))),
             )
             .unwrap(),
         )
     }
 }
 
+impl Handler<SetSeason> for RPSManager {
+    type Result = ();
+    fn handle(&mut self, msg: SetSeason, _: &mut Self::Context) -> Self::Result {
+        info!("{}{:?}", "NEW SEASON : ".purple(), msg.season_id);
+        self.season_id = Some(msg.season_id);
+    }
+}
+
+impl Handler<Disconnect> for RPSManager {
+    type Result = ();
+    fn handle(&mut self, msg: Disconnect, ctx: &mut Self::Context) -> Self::Result {
+        // Only drop the player from their games once their last connection closes
+        if self.sessions.remove(&msg.session_id, &msg.connection_id) {
+            self.queue.remove(&msg.session_id);
+            self.disconnect_player(&msg.session_id, ctx);
+        }
+    }
+}
+
 impl Handler<RPSData> for RPSManager {
     type Result = RPSData;
-    fn handle(&mut self, msg: RPSData, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: RPSData, ctx: &mut Self::Context) -> Self::Result {
         match msg {
             RPSData::Init(msg) => {
-                RPSData::State(self.register_game(msg.players, msg.host, msg.gg_score))
+                let rules = match msg.variant.rules() {
+                    Ok(rules) => rules,
+                    Err(e) => {
+                        return RPSData::Error(SocketError::new(
+                            ErrorCode::InvalidRules,
+                            Some("rps"),
+                            e,
+                        ))
+                    }
+                };
+                let mut game = RPS::new(
+                    vec![msg.host.clone()],
+                    msg.host,
+                    &Uuid::new_v4().to_string(),
+                    msg.gg_score,
+                    msg.timeout_penalty,
+                    rules,
+                );
+                game.commit_reveal = msg.commit_reveal;
+                let game = self.start_game(game, ctx);
+                self.invite_players(&game.id, msg.players, ctx);
+                RPSData::State(self.games.get(&game.id).unwrap().clone())
+            }
+            RPSData::Queue(msg) => {
+                let request = Queue {
+                    players: msg.players.clamp(2, MAX_QUEUE_PLAYERS),
+                    gg_score: msg.gg_score.max(1),
+                    ..msg
+                };
+                let entry = request.clone();
+                // Wait for the rating so queue requests are handled in order
+                self.db_manager
+                    .send(LoadRating {
+                        user_id: entry.sender_id.clone(),
+                    })
+                    .into_actor(self)
+                    .map(move |res, act, ctx| {
+                        act.queue.push(QueueEntry {
+                            user_id: entry.sender_id,
+                            players: entry.players,
+                            gg_score: entry.gg_score,
+                            rating_band: entry.rating_band,
+                            rating: res.unwrap_or(DEFAULT_RATING),
+                        });
+                        act.match_players(ctx);
+                    })
+                    .wait(ctx);
+                RPSData::Queued(request)
+            }
+            RPSData::Tournament(msg) => self.tournament_action(msg, ctx),
+            RPSData::Dequeue(msg) => {
+                if self.queue.remove(&msg.sender_id) {
+                    return RPSData::Dequeued;
+                }
+                RPSData::None
             }
             RPSData::Action(msg) => {
-                let game = self.games.get_mut(&msg.game_id).unwrap();
+                let game = match self.games.get_mut(&msg.game_id) {
+                    Some(game) => game,
+                    None => {
+                        return RPSData::Error(SocketError::new(
+                            ErrorCode::NotFound,
+                            Some("rps"),
+                            format!("There is no game with the ID {}", msg.game_id),
+                        ))
+                    }
+                };
                 match msg.action {
                     RPSAction::Join => {
                         // Only send the state when the player is joining the game for the first time
@@ -123,101 +978,190 @@ impl Handler<RPSData> for RPSManager {
                         {
                             game.connections.insert(msg.sender_id.clone());
 
+                            // Stop the grace period if the player is reconnecting
+                            if let Some(handle) = self
+                                .grace_periods
+                                .remove(&(msg.game_id.clone(), msg.sender_id.clone()))
+                            {
+                                ctx.cancel_future(handle);
+                            }
+
+                            // Start the clock once there's someone to play against
+                            if !self.round_timers.contains_key(&msg.game_id) {
+                                self.start_round(&msg.game_id, ctx);
+                            }
+
                             let game = self.games.get(&msg.game_id).unwrap();
                             self.room_broadcast(
                                 &game,
-                                RPSData::Update(Update {
-                                    game_id: game.id.clone(),
-                                    event: Event::PlayerConnected(msg.sender_id.clone()),
-                                }),
+                                RPSData::Update(Update::new(
+                                    &game.id,
+                                    Event::PlayerConnected(msg.sender_id.clone()),
+                                )),
                             );
                             return RPSData::State(game.clone());
                         }
+
+                        // Anyone else watches the game
+                        if !game.player_ids.contains(&msg.sender_id)
+                            && !game.game_over
+                            && game.spectators.insert(msg.sender_id.clone())
+                        {
+                            let game = self.games.get(&msg.game_id).unwrap();
+                            self.room_broadcast(
+                                game,
+                                RPSData::Update(Update::new(
+                                    &game.id,
+                                    Event::SpectatorJoined(msg.sender_id.clone()),
+                                )),
+                            );
+                            return RPSData::State(game.spectator_view());
+                        }
+                        RPSData::None
+                    }
+                    RPSAction::Accept => {
+                        if !game.accept_invite(&msg.sender_id) {
+                            return RPSData::None;
+                        }
+                        self.db_manager.do_send(StoreGamePlayer {
+                            game_id: msg.game_id.clone(),
+                            user_id: msg.sender_id.clone(),
+                        });
+                        let game = self.games.get(&msg.game_id).unwrap();
+                        self.room_broadcast(
+                            game,
+                            RPSData::Update(Update::new(
+                                &game.id,
+                                Event::InviteAccepted(msg.sender_id.clone()),
+                            )),
+                        );
+                        RPSData::State(game.clone())
+                    }
+                    RPSAction::Decline => {
+                        if game.invited.remove(&msg.sender_id) {
+                            let game = self.games.get(&msg.game_id).unwrap();
+                            self.send_host(game, Event::InviteDeclined(msg.sender_id.clone()));
+                        }
                         RPSData::None
                     }
                     RPSAction::FastMode(flag) => {
                         if msg.sender_id == game.host {
                             game.toggle_fast(flag);
+                            // Restart the current round with the new round time
+                            self.start_round(&msg.game_id, ctx);
                         }
 
                         let game = self.games.get(&msg.game_id).unwrap();
                         self.room_broadcast(
                             &game,
-                            RPSData::Update(Update {
-                                game_id: game.id.clone(),
-                                event: Event::FastToggled(game.fast_mode),
-                            }),
+                            RPSData::Update(Update::new(
+                                &game.id,
+                                Event::FastToggled(game.fast_mode),
+                            )),
                         );
                         RPSData::None
                     }
+                    RPSAction::Commit(hash) => {
+                        if !game.commit_reveal {
+                            return RPSData::Error(SocketError::new(
+                                ErrorCode::Forbidden,
+                                Some("rps"),
+                                "The game doesn't use commit-reveal".to_string(),
+                            ));
+                        }
+                        if !game.commit(&msg.sender_id, hash) {
+                            return RPSData::None;
+                        }
+                        let reveal = game.all_committed();
+
+                        let game = self.games.get(&msg.game_id).unwrap();
+                        self.room_broadcast(
+                            game,
+                            RPSData::Update(Update::new(
+                                &game.id,
+                                Event::Committed(msg.sender_id.clone()),
+                            )),
+                        );
+                        if reveal {
+                            self.room_broadcast(
+                                game,
+                                RPSData::Update(Update::new(&game.id, Event::RevealStarted)),
+                            );
+                        }
+                        RPSData::None
+                    }
+                    RPSAction::Reveal { choice, nonce } => {
+                        if !game.commit_reveal || !game.all_committed() {
+                            return RPSData::Error(SocketError::new(
+                                ErrorCode::Forbidden,
+                                Some("rps"),
+                                "Wait for every player to commit before revealing".to_string(),
+                            ));
+                        }
+                        if !game.verify_reveal(&msg.sender_id, &choice, &nonce) {
+                            warn!(
+                                "{}{:?}",
+                                "REVEAL DOESN'T MATCH COMMITMENT : ".red(),
+                                msg.sender_id
+                            );
+                            return RPSData::Error(SocketError::new(
+                                ErrorCode::CommitmentMismatch,
+                                Some("rps"),
+                                "The move and nonce don't match your commitment".to_string(),
+                            ));
+                        }
+                        let rps = match game.rules.parse_move(&choice) {
+                            Some(rps) => rps,
+                            None => {
+                                return RPSData::Error(SocketError::new(
+                                    ErrorCode::InvalidMove,
+                                    Some("rps"),
+                                    format!(
+                                        "{} isn't a move of {}, pick one of {:?}",
+                                        choice, game.rules.name, game.rules.moves
+                                    ),
+                                ))
+                            }
+                        };
+                        game.reveals.insert(msg.sender_id.clone(), nonce);
+                        // Only resolves once every player revealed
+                        if let Some(resolve) = game.choose_rps(rps, msg.sender_id.clone()) {
+                            self.resolve_round(&msg.game_id, resolve, ctx);
+                        }
+                        RPSData::None
+                    }
                     RPSAction::Choose(rps) => {
+                        if !game.player_ids.contains(&msg.sender_id) {
+                            return RPSData::Error(SocketError::new(
+                                ErrorCode::Forbidden,
+                                Some("rps"),
+                                "Spectators can't choose a move".to_string(),
+                            ));
+                        }
+                        if game.commit_reveal {
+                            return RPSData::Error(SocketError::new(
+                                ErrorCode::Forbidden,
+                                Some("rps"),
+                                "The game uses commit-reveal, commit to your move first"
+                                    .to_string(),
+                            ));
+                        }
+                        let rps = match game.rules.parse_move(&rps) {
+                            Some(rps) => rps,
+                            None => {
+                                return RPSData::Error(SocketError::new(
+                                    ErrorCode::InvalidMove,
+                                    Some("rps"),
+                                    format!(
+                                        "{} isn't a move of {}, pick one of {:?}",
+                                        rps, game.rules.name, game.rules.moves
+                                    ),
+                                ))
+                            }
+                        };
                         // If the game can be resolved
                         if let Some(resolve) = game.choose_rps(rps, msg.sender_id.clone()) {
-                            // Drain choices from game
-                            let choices: Vec<(String, char)> = game.choices.drain().collect();
-                            match resolve {
-                                RpsResolve::Exclude(losers) => {
-                                    // Shadow the mutable reference
-                                    let game = self.games.get(&msg.game_id).unwrap();
-                                    // Broadcast choices
-                                    self.room_broadcast(
-                                        &game,
-                                        RPSData::Update(Update {
-                                            game_id: game.id.clone(),
-                                            event: Event::Choices(choices.clone()),
-                                        }),
-                                    );
-                                    // Broadcast losers
-                                    self.room_broadcast(
-                                        &game,
-                                        RPSData::Update(Update {
-                                            game_id: game.id.clone(),
-                                            event: Event::Exclude(losers.clone()),
-                                        }),
-                                    );
-                                    return RPSData::None;
-                                }
-                                RpsResolve::Winner(winner) => {
-                                    game.reset_excluded();
-                                    // Shadow the mutable reference
-                                    let game = self.games.get(&msg.game_id).unwrap();
-                                    // Broadcast choices
-                                    self.room_broadcast(
-                                        &game,
-                                        RPSData::Update(Update {
-                                            game_id: game.id.clone(),
-                                            event: Event::Choices(choices.clone()),
-                                        }),
-                                    );
-                                    // Broadcast winner
-                                    self.room_broadcast(
-                                        &game,
-                                        RPSData::Update(Update {
-                                            game_id: game.id.clone(),
-                                            event: Event::Winner(winner.clone()),
-                                        }),
-                                    );
-
-                                    // If the score threshold is reached
-                                    if game.scores.get(&winner).unwrap() >= &game.gg_score {
-                                        info!("MAXIMUM SCORE REACHED -- WINNER : {:?}", winner);
-                                        let game = self.games.get_mut(&msg.game_id).unwrap();
-                                        game.end();
-
-                                        self.db_manager.do_send(StoreHoFEntry { user_id: winner });
-
-                                        let game = self.games.get(&msg.game_id).unwrap();
-                                        self.room_broadcast(
-                                            &game,
-                                            RPSData::Update(Update {
-                                                game_id: game.id.clone(),
-                                                event: Event::GG(game.id.clone()),
-                                            }),
-                                        );
-                                    }
-                                    return RPSData::None;
-                                }
-                            }
+                            self.resolve_round(&msg.game_id, resolve, ctx);
                         }
                         RPSData::None
                     }
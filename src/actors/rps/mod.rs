@@ -1,3 +1,6 @@
 pub mod models;
 pub mod game;
-pub mod manager;
\ No newline at end of file
+pub mod manager;
+pub mod queue;
+pub mod rules;
+pub mod tournament;
\ No newline at end of file
@@ -1,5 +1,9 @@
 use super::game::RPS;
+use super::rules::Variant;
+use super::tournament::{Format, Tournament};
+use crate::actors::models::messages::error::SocketError;
 use actix::prelude::*;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, collections::HashSet};
 
@@ -19,46 +23,162 @@ pub enum RPSData {
     // Client
     Init(Init),
     Action(Action),
+    Queue(Queue),
+    Dequeue(Dequeue),
+    Tournament(TournamentRequest),
     // Server
     State(RPS),
     Update(Update),
     Rooms(Vec<RPS>),
+    /// The player is waiting in the matchmaking queue
+    Queued(Queue),
+    /// The player left the matchmaking queue
+    Dequeued,
+    /// The matchmaking queue found a game for the player
+    Matched(RPS),
+    /// The tournament changed, sent to its entrants or to everyone while it takes registrations
+    Bracket(Tournament),
+    /// Every tournament the user can see
+    Tournaments(Vec<Tournament>),
+    /// The message was rejected, sent to the client with the `error` header
+    Error(SocketError),
     None
 }
 impl actix::Message for RPSData {
     type Result = Self;
 }
 
+impl RPSData {
+    /// Returns the user the client message claims to come from, `None` for server messages
+    pub fn sender_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::Init(init) => Some(&mut init.host),
+            Self::Action(action) => Some(&mut action.sender_id),
+            Self::Queue(queue) => Some(&mut queue.sender_id),
+            Self::Dequeue(dequeue) => Some(&mut dequeue.sender_id),
+            Self::Tournament(request) => Some(&mut request.sender_id),
+            _ => None,
+        }
+    }
+}
+
 /// Sent to the client
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Update {
     pub game_id: String,
     pub event: Event,
+    /// When the current round times out, set by the manager when broadcasting
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+impl Update {
+    pub fn new(game_id: &str, event: Event) -> Self {
+        Self {
+            game_id: game_id.to_string(),
+            event,
+            deadline: None,
+        }
+    }
 }
 
 /// Indicates the type of event that occurred as a consequence of a player's action
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Event {
     PlayerConnected(String),
+    /// The user started watching the game
+    SpectatorJoined(String),
+    /// The spectator's last connection closed
+    SpectatorLeft(String),
+    /// The player lost their connection and has a grace period to rejoin
+    PlayerDisconnected(String),
+    /// The player didn't rejoin in time and is out of the game
+    Forfeit(String),
+    /// Every player forfeited, the game is removed
+    Cancelled(String),
+    /// The player committed to their move for the round
+    Committed(String),
+    /// Every player committed, they can now reveal their move
+    RevealStarted,
+    /// A new round started, the deadline is in the update
+    RoundStarted,
+    /// The host invited the player to the game
+    Invite(String),
+    /// The player accepted their invitation and is now in the game
+    InviteAccepted(String),
+    /// The player declined their invitation
+    InviteDeclined(String),
+    /// The player didn't answer their invitation in time
+    InviteExpired(String),
+    /// The round timed out before these players made their choice
+    TimedOut(HashSet<String>),
     FastToggled(bool),
-    Choices(Vec<(String, char)>),
+    Choices(Vec<(String, String)>),
     Exclude(HashSet<String>),
     Winner(String),
     GG(String)
 }
 
-/// Message used to instantiate an rps game
+/// Sent when an admin starts a new season, new games are tagged with it
+#[derive(Message, Debug, Serialize, Deserialize, Clone)]
+#[rtype(result = "()")]
+pub struct SetSeason {
+    pub season_id: i32,
+}
+
+/// Message used to instantiate an rps game, the players other than the host are invited
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Init {
+    /// Checked against the session and filled in if omitted
+    #[serde(default)]
     pub host: String,
     pub players: Vec<String>,
-    pub gg_score: usize
+    pub gg_score: usize,
+    #[serde(default)]
+    pub timeout_penalty: TimeoutPenalty,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Players commit to their move before revealing it
+    #[serde(default)]
+    pub commit_reveal: bool,
+}
+
+/// What happens to players who don't make a choice before the round times out
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeoutPenalty {
+    /// A random choice is made for them. Commit-reveal games exclude them instead.
+    #[default]
+    RandomPick,
+    /// They forfeit the game
+    Forfeit,
+    /// They are excluded from the round
+    Exclude,
+}
+
+/// Sent by the client to wait for a game with other players looking for the same kind of game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Queue {
+    #[serde(default)]
+    pub sender_id: String,
+    /// The amount of players the game should have, including the sender
+    pub players: usize,
+    pub gg_score: usize,
+    /// Only match with players whose rating is at most this far from the sender's
+    #[serde(default)]
+    pub rating_band: Option<f64>,
+}
+
+/// Sent by the client to leave the matchmaking queue
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Dequeue {
+    #[serde(default)]
+    pub sender_id: String,
 }
 
 /// Sent by the client
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Action {
     pub game_id: String,
+    #[serde(default)]
     pub sender_id: String,
     pub action: RPSAction,
 }
@@ -66,10 +186,46 @@ pub struct Action {
 /// Data sent by the client indicating the specified action
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum RPSAction {
-    /// Inserts the player into the rps connections
+    /// Inserts the player into the rps connections, other users join as spectators
     Join,
     /// Maps a player to their choice
-    Choose(char),
+    Choose(String),
     /// Toggles fast mode
     FastMode(bool),
+    /// Accepts the invitation to the game
+    Accept,
+    /// Declines the invitation to the game
+    Decline,
+    /// Commits to a move with the hex encoded SHA-256 hash of `move:nonce`, in commit-reveal
+    /// games
+    Commit(String),
+    /// Reveals the committed move once every player committed
+    Reveal { choice: String, nonce: String },
+}
+
+/// Sent by the client to create, join or start a tournament
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TournamentRequest {
+    #[serde(default)]
+    pub sender_id: String,
+    pub action: TournamentAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum TournamentAction {
+    /// Opens a tournament for registrations, the sender hosts and enters it
+    Create {
+        name: String,
+        format: Format,
+        /// Every match is a best of N series
+        best_of: usize,
+        #[serde(default)]
+        variant: Variant,
+    },
+    /// Registers the sender to the tournament with the given ID
+    Register(String),
+    /// Withdraws the sender's registration before the tournament starts
+    Withdraw(String),
+    /// Seeds the entrants and spawns the first round, host only
+    Start(String),
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+/// A player waiting in the matchmaking queue.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueueEntry {
+    pub user_id: String,
+    /// The amount of players the game should have
+    pub players: usize,
+    pub gg_score: usize,
+    /// The largest rating difference the player accepts with the other players, any if `None`
+    pub rating_band: Option<f64>,
+    /// The player's rating when they joined the queue
+    pub rating: f64,
+}
+
+impl QueueEntry {
+    /// Returns true if both players want the same kind of game
+    fn compatible(&self, other: &QueueEntry) -> bool {
+        self.players == other.players && self.gg_score == other.gg_score
+    }
+}
+
+/// Players waiting for a game, in the order they joined.
+#[derive(Debug, Default)]
+pub struct MatchmakingQueue {
+    entries: Vec<QueueEntry>,
+}
+
+impl MatchmakingQueue {
+    /// Puts the player at the back of the queue, replacing their previous request
+    pub fn push(&mut self, entry: QueueEntry) {
+        self.remove(&entry.user_id);
+        self.entries.push(entry);
+    }
+
+    /// Removes the player from the queue. Returns true if they were in it.
+    pub fn remove(&mut self, user_id: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.user_id != user_id);
+        self.entries.len() != len
+    }
+
+    /// Finds the first group of compatible players that fills a game and takes them out of
+    /// the queue. Players that waited the longest are matched first. Every player in the group
+    /// is within the rating band of all the others.
+    pub fn find_match(&mut self) -> Option<Vec<QueueEntry>> {
+        let group = self.entries.iter().enumerate().find_map(|(i, anchor)| {
+            let mut group = vec![i];
+            for (j, candidate) in self.entries.iter().enumerate().skip(i + 1) {
+                if group.len() == anchor.players {
+                    break;
+                }
+                let fits = anchor.compatible(candidate)
+                    && group
+                        .iter()
+                        .all(|k| Self::within_band(&self.entries[*k], candidate));
+                if fits {
+                    group.push(j);
+                }
+            }
+            (group.len() == anchor.players).then_some(group)
+        })?;
+
+        // Remove from the back so the indices stay valid
+        let mut matched: Vec<QueueEntry> = group
+            .into_iter()
+            .rev()
+            .map(|k| self.entries.remove(k))
+            .collect();
+        matched.reverse();
+        Some(matched)
+    }
+
+    /// Returns true if the two players accept each other's rating
+    fn within_band(a: &QueueEntry, b: &QueueEntry) -> bool {
+        let difference = (a.rating - b.rating).abs();
+        a.rating_band.is_none_or(|band| difference <= band)
+            && b.rating_band.is_none_or(|band| difference <= band)
+    }
+}
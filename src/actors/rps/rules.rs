@@ -0,0 +1,159 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The most moves a cyclic variant can have, building its rules takes quadratic time
+pub const MAX_MOVES: usize = 101;
+/// The longest a move's name can be
+pub const MAX_MOVE_LENGTH: usize = 32;
+
+/// The rules a game is played with, chosen by the host in `Init`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub enum Variant {
+    /// Rock, paper, scissors
+    #[default]
+    Classic,
+    /// Rock, paper, scissors, lizard, Spock
+    Rpsls,
+    /// Any odd number of moves in a circle, each move beats the half of the circle after it
+    Cyclic(Vec<String>),
+}
+
+impl Variant {
+    /// Builds the rule set of the variant. Fails if the moves of a cyclic variant are invalid.
+    pub fn rules(&self) -> Result<RuleSet, String> {
+        match self {
+            Self::Classic => RuleSet::cyclic("classic", &["rock", "scissors", "paper"]),
+            Self::Rpsls => {
+                RuleSet::cyclic("rpsls", &["rock", "scissors", "lizard", "paper", "spock"])
+            }
+            Self::Cyclic(moves) => {
+                let moves: Vec<&str> = moves.iter().map(String::as_str).collect();
+                RuleSet::cyclic("cyclic", &moves)
+            }
+        }
+    }
+}
+
+/// Named moves along with the graph of which move beats which.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    pub name: String,
+    pub moves: Vec<String>,
+    /// Maps the index of every move to the indices of the moves it beats
+    pub beats: Vec<Vec<usize>>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Variant::Classic.rules().unwrap()
+    }
+}
+
+impl RuleSet {
+    /// Builds a balanced rule set where each move beats the `(n - 1) / 2` moves following it,
+    /// wrapping around. The number of moves has to be odd so every pair of moves has a winner.
+    pub fn cyclic(name: &str, moves: &[&str]) -> Result<RuleSet, String> {
+        if moves.len() < 3 || moves.len() > MAX_MOVES || moves.len().is_multiple_of(2) {
+            return Err(format!(
+                "A cyclic variant needs an odd number of moves, 3 to {}, got {}",
+                MAX_MOVES,
+                moves.len()
+            ));
+        }
+        if moves
+            .iter()
+            .any(|m| m.trim().chars().count() > MAX_MOVE_LENGTH)
+        {
+            return Err(format!(
+                "Moves are at most {} characters long",
+                MAX_MOVE_LENGTH
+            ));
+        }
+        let moves: Vec<String> = moves.iter().map(|m| m.trim().to_lowercase()).collect();
+        let unique: HashSet<&String> = moves.iter().collect();
+        if unique.len() != moves.len() || moves.iter().any(|m| m.is_empty()) {
+            return Err("Moves have to be unique and not empty".to_string());
+        }
+        let n = moves.len();
+        let beats = (0..n)
+            .map(|i| (1..=(n - 1) / 2).map(|offset| (i + offset) % n).collect())
+            .collect();
+        Ok(RuleSet {
+            name: name.to_string(),
+            moves,
+            beats,
+        })
+    }
+
+    /// Returns the name of the move the input refers to, either by its full name or by an
+    /// unambiguous prefix, so `r` is rock in the classic variant.
+    pub fn parse_move(&self, input: &str) -> Option<String> {
+        let input = input.trim().to_lowercase();
+        if input.is_empty() {
+            return None;
+        }
+        if self.moves.contains(&input) {
+            return Some(input);
+        }
+        let mut matches = self.moves.iter().filter(|m| m.starts_with(&input));
+        match (matches.next(), matches.next()) {
+            (Some(m), None) => Some(m.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns true if move `a` beats move `b`
+    pub fn beats(&self, a: &str, b: &str) -> bool {
+        match (self.index(a), self.index(b)) {
+            (Some(a), Some(b)) => self.beats[a].contains(&b),
+            _ => false,
+        }
+    }
+
+    pub fn random_move(&self) -> String {
+        let mut rng = rand::thread_rng();
+        self.moves[rng.gen_range(0..self.moves.len())].clone()
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.moves.iter().position(|m| m == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpsls_follows_the_usual_rules() {
+        let rules = Variant::Rpsls.rules().unwrap();
+        assert!(rules.beats("scissors", "paper"));
+        assert!(rules.beats("paper", "rock"));
+        assert!(rules.beats("rock", "lizard"));
+        assert!(rules.beats("lizard", "spock"));
+        assert!(rules.beats("spock", "scissors"));
+        assert!(rules.beats("scissors", "lizard"));
+        assert!(rules.beats("lizard", "paper"));
+        assert!(rules.beats("paper", "spock"));
+        assert!(rules.beats("spock", "rock"));
+        assert!(rules.beats("rock", "scissors"));
+        assert!(!rules.beats("rock", "paper"));
+        assert_eq!(rules.parse_move("l"), Some("lizard".to_string()));
+        assert_eq!(rules.parse_move("s"), None);
+        assert!(Variant::Cyclic(vec!["a".into(), "b".into()])
+            .rules()
+            .is_err());
+    }
+
+    #[test]
+    fn cyclic_variants_are_bounded() {
+        let moves = |n: usize| (0..n).map(|i| format!("m{}", i)).collect::<Vec<_>>();
+        assert!(Variant::Cyclic(moves(MAX_MOVES)).rules().is_ok());
+        assert!(Variant::Cyclic(moves(MAX_MOVES + 2)).rules().is_err());
+
+        let mut long = moves(3);
+        long[0] = "x".repeat(MAX_MOVE_LENGTH + 1);
+        assert!(Variant::Cyclic(long).rules().is_err());
+    }
+}
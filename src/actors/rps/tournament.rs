@@ -0,0 +1,410 @@
+use super::rules::Variant;
+use crate::services::rating::DEFAULT_RATING;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The longest series a tournament match can be
+pub const MAX_BEST_OF: usize = 9;
+
+/// How players are paired and eliminated.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Out after the first lost match
+    SingleElimination,
+    /// Out after the second lost match
+    DoubleElimination,
+    /// Everyone plays a fixed number of rounds against players with a similar record
+    Swiss,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentStatus {
+    Registering,
+    Running,
+    Finished,
+}
+
+/// A registered player along with their record in the tournament.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Entrant {
+    pub user_id: String,
+    /// 1 is the best rated player
+    pub seed: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub opponents: Vec<String>,
+}
+
+/// A single match of the bracket, played as an `RPS` game.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Match {
+    pub round: usize,
+    /// A single player means they got a bye
+    pub players: Vec<String>,
+    pub game_id: Option<String>,
+    /// Empty if every player forfeited
+    pub winner: Option<String>,
+    pub finished: bool,
+}
+
+/// Tournament instance, spawns its matches as `RPS` games through the manager.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tournament {
+    pub id: String,
+    pub name: String,
+    pub host: String,
+    pub format: Format,
+    /// Every match is a best of N series
+    pub best_of: usize,
+    pub variant: Variant,
+    pub status: TournamentStatus,
+    /// Registered players, in bracket order once the tournament started
+    pub entrants: Vec<Entrant>,
+    pub matches: Vec<Match>,
+    pub round: usize,
+    /// How many rounds a Swiss tournament lasts
+    pub rounds: usize,
+    pub champion: Option<String>,
+}
+
+impl Tournament {
+    pub fn new(
+        id: &str,
+        name: String,
+        host: String,
+        format: Format,
+        best_of: usize,
+        variant: Variant,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            name,
+            host,
+            format,
+            // An even series could end in a draw
+            best_of: best_of.clamp(1, MAX_BEST_OF) | 1,
+            variant,
+            status: TournamentStatus::Registering,
+            entrants: vec![],
+            matches: vec![],
+            round: 0,
+            rounds: 0,
+            champion: None,
+        }
+    }
+
+    /// The amount of games a player has to win to take a match
+    pub fn gg_score(&self) -> usize {
+        self.best_of / 2 + 1
+    }
+
+    /// Everyone can see a tournament taking registrations, only its entrants afterwards
+    pub fn is_visible_to(&self, user_id: &str) -> bool {
+        self.status == TournamentStatus::Registering || self.is_entrant(user_id)
+    }
+
+    pub fn is_entrant(&self, user_id: &str) -> bool {
+        self.entrants
+            .iter()
+            .any(|entrant| entrant.user_id == user_id)
+    }
+
+    /// Registers the player. Returns false if registrations are closed or they already are.
+    pub fn register(&mut self, user_id: &str) -> bool {
+        if self.status != TournamentStatus::Registering || self.is_entrant(user_id) {
+            return false;
+        }
+        self.entrants.push(Entrant {
+            user_id: user_id.to_string(),
+            seed: 0,
+            wins: 0,
+            losses: 0,
+            opponents: vec![],
+        });
+        true
+    }
+
+    pub fn unregister(&mut self, user_id: &str) -> bool {
+        if self.status != TournamentStatus::Registering {
+            return false;
+        }
+        let len = self.entrants.len();
+        self.entrants.retain(|entrant| entrant.user_id != user_id);
+        self.entrants.len() != len
+    }
+
+    /// Closes registrations, seeds the players by rating and pairs the first round. Returns
+    /// the indices of the matches to play.
+    pub fn start(&mut self, ratings: &HashMap<String, f64>) -> Vec<usize> {
+        self.status = TournamentStatus::Running;
+        let rating = |entrant: &Entrant| {
+            ratings
+                .get(&entrant.user_id)
+                .copied()
+                .unwrap_or(DEFAULT_RATING)
+        };
+        self.entrants
+            .sort_by(|a, b| rating(b).partial_cmp(&rating(a)).unwrap());
+        for (i, entrant) in self.entrants.iter_mut().enumerate() {
+            entrant.seed = i + 1;
+        }
+        self.rounds = (usize::BITS - (self.entrants.len() - 1).leading_zeros()).max(1) as usize;
+
+        if self.format == Format::Swiss {
+            return self.advance();
+        }
+
+        // Spread the seeds so the best players meet as late as possible, the top seeds get
+        // the byes when the bracket isn't full
+        let size = self.entrants.len().next_power_of_two();
+        let slots: Vec<Option<Entrant>> = bracket_order(size)
+            .into_iter()
+            .map(|seed| self.entrants.get(seed).cloned())
+            .collect();
+        self.round = 1;
+        for pair in slots.chunks(2) {
+            let players = pair.iter().flatten().map(|e| e.user_id.clone()).collect();
+            self.push_match(players);
+        }
+        self.entrants = slots.into_iter().flatten().collect();
+        let playable = self.playable();
+        if playable.is_empty() {
+            return self.advance();
+        }
+        playable
+    }
+
+    /// Records the result of the match played in the game. Returns true if it was the last
+    /// match of the round.
+    pub fn record_result(&mut self, game_id: &str, winner: Option<String>) -> bool {
+        let index = match self
+            .matches
+            .iter()
+            .position(|m| m.game_id.as_deref() == Some(game_id) && !m.finished)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let players = self.matches[index].players.clone();
+        self.matches[index].winner = winner.clone();
+        self.matches[index].finished = true;
+        for player in &players {
+            let entrant = self.entrant_mut(player);
+            if winner.as_ref() == Some(player) {
+                entrant.wins += 1;
+            } else {
+                entrant.losses += 1;
+            }
+            entrant
+                .opponents
+                .extend(players.iter().filter(|p| *p != player).cloned());
+        }
+        self.matches
+            .iter()
+            .filter(|m| m.round == self.round)
+            .all(|m| m.finished)
+    }
+
+    /// Pairs the next round, or crowns the champion if the tournament is over. Rounds made
+    /// only of byes are skipped. Returns the indices of the matches to play.
+    pub fn advance(&mut self) -> Vec<usize> {
+        loop {
+            if let Some(champion) = self.finished() {
+                self.status = TournamentStatus::Finished;
+                self.champion = champion;
+                return vec![];
+            }
+            self.round += 1;
+            let pairs = match self.format {
+                Format::Swiss => self.pair_swiss(),
+                _ => self.pair_elimination(),
+            };
+            for players in pairs {
+                self.push_match(players);
+            }
+            let playable = self.playable();
+            if !playable.is_empty() {
+                return playable;
+            }
+        }
+    }
+
+    /// Returns `Some` with the champion if the tournament is over
+    fn finished(&self) -> Option<Option<String>> {
+        match self.format {
+            Format::Swiss => (self.round >= self.rounds).then(|| {
+                self.entrants
+                    .iter()
+                    .max_by(|a, b| a.wins.cmp(&b.wins).then(b.seed.cmp(&a.seed)))
+                    .map(|entrant| entrant.user_id.clone())
+            }),
+            _ => {
+                let alive = self.alive();
+                (alive.len() <= 1).then(|| alive.first().map(|e| e.user_id.clone()))
+            }
+        }
+    }
+
+    /// The players that can still win an elimination tournament
+    fn alive(&self) -> Vec<&Entrant> {
+        let lives = match self.format {
+            Format::DoubleElimination => 2,
+            _ => 1,
+        };
+        self.entrants.iter().filter(|e| e.losses < lives).collect()
+    }
+
+    /// Pairs neighbours in bracket order among players with the same amount of losses, so
+    /// the losers bracket plays separately. The last two players meet in the final, and
+    /// again if the undefeated one loses it.
+    fn pair_elimination(&self) -> Vec<Vec<String>> {
+        let alive = self.alive();
+        if alive.len() == 2 {
+            return vec![alive.iter().map(|e| e.user_id.clone()).collect()];
+        }
+        let mut pairs = vec![];
+        for losses in 0..2 {
+            let group: Vec<String> = alive
+                .iter()
+                .filter(|e| e.losses == losses)
+                .map(|e| e.user_id.clone())
+                .collect();
+            pairs.extend(group.chunks(2).map(|pair| pair.to_vec()));
+        }
+        pairs
+    }
+
+    /// Pairs players with the same amount of wins, avoiding rematches when possible. The
+    /// odd player out gets a bye.
+    fn pair_swiss(&self) -> Vec<Vec<String>> {
+        let mut ranked: Vec<&Entrant> = self.entrants.iter().collect();
+        ranked.sort_by(|a, b| b.wins.cmp(&a.wins).then(a.seed.cmp(&b.seed)));
+        let mut pairs = vec![];
+        while let Some(player) = ranked.first().copied() {
+            ranked.remove(0);
+            let opponent = ranked
+                .iter()
+                .position(|e| !player.opponents.contains(&e.user_id))
+                .or(if ranked.is_empty() { None } else { Some(0) });
+            match opponent {
+                Some(i) => {
+                    let opponent = ranked.remove(i);
+                    pairs.push(vec![player.user_id.clone(), opponent.user_id.clone()]);
+                }
+                None => pairs.push(vec![player.user_id.clone()]),
+            }
+        }
+        pairs
+    }
+
+    /// Adds a match to the current round, a player without opponent wins by bye
+    fn push_match(&mut self, players: Vec<String>) {
+        let bye = players.len() == 1;
+        if bye {
+            self.entrant_mut(&players[0]).wins += 1;
+        }
+        self.matches.push(Match {
+            round: self.round,
+            winner: if bye { players.first().cloned() } else { None },
+            finished: bye,
+            players,
+            game_id: None,
+        });
+    }
+
+    /// The matches of the current round that need to be played
+    pub fn playable(&self) -> Vec<usize> {
+        self.matches
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.round == self.round && !m.finished)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn entrant_mut(&mut self, user_id: &str) -> &mut Entrant {
+        self.entrants
+            .iter_mut()
+            .find(|entrant| entrant.user_id == user_id)
+            .unwrap()
+    }
+}
+
+/// Returns the seed indices in bracket order, so seed 1 meets seed `size` in the first round
+/// and can only meet seed 2 in the final.
+fn bracket_order(size: usize) -> Vec<usize> {
+    if size <= 1 {
+        return vec![0];
+    }
+    bracket_order(size / 2)
+        .into_iter()
+        .flat_map(|seed| [seed, size - 1 - seed])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays the tournament out with the better seed winning every match
+    fn play_out(format: Format, players: usize) -> Tournament {
+        let mut tournament =
+            Tournament::new("t", "Cup".into(), "p0".into(), format, 3, Variant::Classic);
+        let mut ratings = HashMap::new();
+        for i in 0..players {
+            tournament.register(&format!("p{}", i));
+            ratings.insert(format!("p{}", i), 2000.0 - i as f64);
+        }
+        let mut matches = tournament.start(&ratings);
+        while !matches.is_empty() {
+            let mut round_over = false;
+            for i in matches {
+                let game_id = format!("g{}", i);
+                tournament.matches[i].game_id = Some(game_id.clone());
+                let winner = tournament.matches[i]
+                    .players
+                    .iter()
+                    .min_by_key(|p| p[1..].parse::<usize>().unwrap())
+                    .cloned();
+                round_over = tournament.record_result(&game_id, winner);
+            }
+            assert!(round_over);
+            matches = tournament.advance();
+        }
+        tournament
+    }
+
+    #[test]
+    fn top_seed_wins_every_format() {
+        let single = play_out(Format::SingleElimination, 5);
+        assert_eq!(single.champion.as_deref(), Some("p0"));
+        // 3 byes and 4 matches
+        assert_eq!(
+            single
+                .matches
+                .iter()
+                .filter(|m| m.game_id.is_some())
+                .count(),
+            4
+        );
+
+        let double = play_out(Format::DoubleElimination, 4);
+        assert_eq!(double.champion.as_deref(), Some("p0"));
+        assert_eq!(double.entrants.iter().filter(|e| e.losses < 2).count(), 1);
+
+        let swiss = play_out(Format::Swiss, 6);
+        assert_eq!(swiss.champion.as_deref(), Some("p0"));
+        assert_eq!(swiss.round, 3);
+    }
+
+    #[test]
+    fn series_are_odd_and_bounded() {
+        let best_of = |n| {
+            Tournament::new("t", "Cup".into(), "p0".into(), Format::Swiss, n, Variant::Classic)
+                .best_of
+        };
+        assert_eq!(best_of(0), 1);
+        assert_eq!(best_of(4), 5);
+        assert_eq!(best_of(usize::MAX), MAX_BEST_OF);
+    }
+}
@@ -14,12 +14,66 @@ pub fn setup_routes(cfg: &mut web::ServiceConfig) {
             .route(web::get().to(routes::users::handler))
             .wrap(crate::middleware::auth::LoggedGuard),
     );
-    // GET /hof
+    // GET /hof?sort=&limit=&offset=&around=
     cfg.service(
         web::resource("/hof")
             .route(web::get().to(routes::hall_of_fame::handler))
             .wrap(crate::middleware::auth::LoggedGuard),
     );
+    // GET /rooms/{id}/messages?before=&limit=
+    cfg.service(
+        web::resource("/rooms/{id}/messages")
+            .route(web::get().to(routes::history::room::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /dm/{user_id}/messages?before=&limit=
+    cfg.service(
+        web::resource("/dm/{user_id}/messages")
+            .route(web::get().to(routes::history::dm::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /messages/{id}/revisions
+    cfg.service(
+        web::resource("/messages/{id}/revisions")
+            .route(web::get().to(routes::history::revisions::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /users/{id}/games
+    cfg.service(
+        web::resource("/users/{id}/games")
+            .route(web::get().to(routes::games::user::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /games/{id}
+    cfg.service(
+        web::resource("/games/{id}")
+            .route(web::get().to(routes::games::replay::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /leaderboards/{today|week|season}?limit=
+    cfg.service(
+        web::resource("/leaderboards/{window}")
+            .route(web::get().to(routes::leaderboards::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /seasons
+    cfg.service(
+        web::resource("/seasons")
+            .route(web::get().to(routes::seasons::list::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // POST /seasons/rollover -- Admins only
+    cfg.service(
+        web::resource("/seasons/rollover")
+            .route(web::post().to(routes::seasons::rollover::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
+    // GET /seasons/{id}/standings?limit=
+    cfg.service(
+        web::resource("/seasons/{id}/standings")
+            .route(web::get().to(routes::seasons::standings::handler))
+            .wrap(crate::middleware::auth::LoggedGuard),
+    );
     // GET /chat -- Upgrades to websocket on success, extracts user info from the authorization JWT so no need for LoggedGuard  
     cfg.service(
         web::resource("/chat")
@@ -2,6 +2,7 @@ use crate::models::error::{AuthenticationError, GlobalError};
 use crate::actors::chat::models::chat_user::ChatUser;
 use crate::services::jwt;
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::HttpMessage;
 use colored::Colorize;
 use futures_util::future::LocalBoxFuture;
 use std::future::{ready, Ready};
@@ -47,6 +48,8 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         match is_authorized(&req) {
             Ok(chat_user) => {
+                // Make the user available to handlers through `web::ReqData<ChatUser>`
+                req.extensions_mut().insert(chat_user.clone());
                 let fut = self.service.call(req);
                 info!(
                     "{}{:?}",
@@ -22,6 +22,8 @@ pub enum GlobalError {
     ActixError(actix_web::Error),
     #[error("INTERNAL SERVER ERROR")]
     R2D2Error,
+    #[error("NOT FOUND")]
+    NotFound,
     #[error("`{0}`")]
     AuthenticationError(AuthenticationError),
     #[error("`{0}`")]
@@ -37,7 +39,9 @@ impl GlobalError {
                 AuthenticationError::UserAlreadyExists => "Username taken".to_string(),
                 AuthenticationError::BadPassword => "Invalid credentials".to_string(),
                 AuthenticationError::InvalidToken => "Token either missing or expired".to_string(),
+                AuthenticationError::Forbidden => "Access denied".to_string(),
             },
+            Self::NotFound => "Not found".to_string(),
             _ => "Internal server error".to_string(),
         }
     }
@@ -58,6 +62,7 @@ impl ResponseError for GlobalError {
     fn status_code(&self) -> reqwest::StatusCode {
         match self {
             Self::AuthenticationError(e) => e.status_code(),
+            Self::NotFound => StatusCode::NOT_FOUND,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -93,6 +98,8 @@ pub enum AuthenticationError {
     BadPassword,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Forbidden")]
+    Forbidden,
 }
 
 impl AuthenticationError {
@@ -102,6 +109,7 @@ impl AuthenticationError {
             Self::UserAlreadyExists => StatusCode::CONFLICT,
             Self::BadPassword => StatusCode::UNAUTHORIZED,
             Self::InvalidToken => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
         }
     }
 }
@@ -0,0 +1,164 @@
+use super::error::GlobalError;
+use super::game_player::{GamePlayer, NewGamePlayer};
+use super::game_round::GameRound;
+use crate::actors::rps::game::RPS;
+use crate::schema::{game_players, games};
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, Insertable, Queryable, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+/// The game is still being played
+pub const GAME_RUNNING: &str = "running";
+/// A player reached the score threshold or everyone else forfeited
+pub const GAME_FINISHED: &str = "finished";
+/// Every player forfeited
+pub const GAME_CANCELLED: &str = "cancelled";
+/// The server stopped while the game was running
+pub const GAME_ABANDONED: &str = "abandoned";
+
+#[derive(Queryable, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub id: String,
+    pub name: String,
+    pub host: String,
+    pub gg_score: i32,
+    pub status: String,
+    pub winner: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub season_id: Option<i32>,
+    pub tournament_id: Option<String>,
+}
+
+/// A past game along with its players and their final scores.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    #[serde(flatten)]
+    pub game: Game,
+    pub players: Vec<GamePlayer>,
+}
+
+/// A game with all its rounds in the order they were played.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameReplay {
+    #[serde(flatten)]
+    pub game: Game,
+    pub players: Vec<GamePlayer>,
+    pub rounds: Vec<GameRound>,
+}
+
+impl Game {
+    pub fn find_by_id(conn: &PgConnection, id: &str) -> Result<Option<Game>, GlobalError> {
+        games::table
+            .find(id)
+            .first::<Game>(conn)
+            .optional()
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Returns all games the user played in, latest first
+    pub fn find_by_player(conn: &PgConnection, user_id: &str) -> Result<Vec<Game>, GlobalError> {
+        games::table
+            .inner_join(game_players::table)
+            .filter(game_players::user_id.eq(user_id))
+            .select(games::all_columns)
+            .order(games::started_at.desc())
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Returns the user's games along with everyone who played in them
+    pub fn find_records(
+        conn: &PgConnection,
+        user_id: &str,
+    ) -> Result<Vec<GameRecord>, GlobalError> {
+        let games = Self::find_by_player(conn, user_id)?;
+        let ids: Vec<&str> = games.iter().map(|game| game.id.as_str()).collect();
+        let players = GamePlayer::find_by_games(conn, &ids)?;
+        Ok(games
+            .into_iter()
+            .map(|game| GameRecord {
+                players: players
+                    .iter()
+                    .filter(|player| player.game_id == game.id)
+                    .cloned()
+                    .collect(),
+                game,
+            })
+            .collect())
+    }
+
+    /// Returns the game with its players and rounds
+    pub fn find_replay(conn: &PgConnection, id: &str) -> Result<Option<GameReplay>, GlobalError> {
+        let game = match Self::find_by_id(conn, id)? {
+            Some(game) => game,
+            None => return Ok(None),
+        };
+        Ok(Some(GameReplay {
+            players: GamePlayer::find_by_games(conn, &[id])?,
+            rounds: GameRound::find_by_game(conn, id)?,
+            game,
+        }))
+    }
+
+    /// Sets the game's final status and winner
+    pub fn end(
+        conn: &PgConnection,
+        id: &str,
+        status: &str,
+        winner: Option<&str>,
+    ) -> Result<usize, GlobalError> {
+        diesel::update(games::table.find(id))
+            .set((
+                games::status.eq(status),
+                games::winner.eq(winner),
+                games::ended_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Marks every game that's still running as abandoned
+    pub fn abandon_running(conn: &PgConnection) -> Result<usize, GlobalError> {
+        diesel::update(games::table.filter(games::status.eq(GAME_RUNNING)))
+            .set((
+                games::status.eq(GAME_ABANDONED),
+                games::ended_at.eq(Utc::now()),
+            ))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "games"]
+pub struct NewGame<'a> {
+    id: &'a str,
+    name: &'a str,
+    host: &'a str,
+    gg_score: i32,
+    season_id: Option<i32>,
+    tournament_id: Option<&'a str>,
+}
+
+impl<'a> NewGame<'a> {
+    /// Stores the game along with its players
+    pub fn store(conn: &PgConnection, game: &'a RPS) -> Result<(), GlobalError> {
+        conn.transaction::<_, GlobalError, _>(|| {
+            diesel::insert_into(games::table)
+                .values(Self {
+                    id: &game.id,
+                    name: &game.name,
+                    host: &game.host,
+                    gg_score: game.gg_score as i32,
+                    season_id: game.season_id,
+                    tournament_id: game.tournament_id.as_deref(),
+                })
+                .execute(conn)?;
+            for user_id in &game.player_ids {
+                NewGamePlayer::store(conn, &game.id, user_id)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,68 @@
+use crate::schema::game_players;
+use diesel::{prelude::*, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use super::error::GlobalError;
+#[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
+pub struct GamePlayer {
+    pub game_id: String,
+    pub user_id: String,
+    pub score: i32,
+    pub forfeited: bool,
+}
+
+impl GamePlayer {
+    pub fn find_by_games(
+        conn: &PgConnection,
+        game_ids: &[&str],
+    ) -> Result<Vec<GamePlayer>, GlobalError> {
+        game_players::table
+            .filter(game_players::game_id.eq_any(game_ids))
+            .order(game_players::score.desc())
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn increment_score(
+        conn: &PgConnection,
+        game_id: &str,
+        user_id: &str,
+    ) -> Result<usize, GlobalError> {
+        diesel::update(game_players::table.find((game_id, user_id)))
+            .set(game_players::score.eq(game_players::score + 1))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn forfeit(
+        conn: &PgConnection,
+        game_id: &str,
+        user_id: &str,
+    ) -> Result<usize, GlobalError> {
+        diesel::update(game_players::table.find((game_id, user_id)))
+            .set(game_players::forfeited.eq(true))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "game_players"]
+pub struct NewGamePlayer<'a> {
+    game_id: &'a str,
+    user_id: &'a str,
+}
+
+impl<'a> NewGamePlayer<'a> {
+    pub fn store(
+        conn: &PgConnection,
+        game_id: &'a str,
+        user_id: &'a str,
+    ) -> Result<usize, GlobalError> {
+        diesel::insert_into(game_players::table)
+            .values(Self { game_id, user_id })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
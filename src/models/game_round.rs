@@ -0,0 +1,56 @@
+use crate::schema::game_rounds;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::error::GlobalError;
+/// A single resolved round of an RPS game.
+#[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
+pub struct GameRound {
+    pub game_id: String,
+    pub round: i32,
+    /// Maps the players to their choice
+    pub choices: Value,
+    /// The players that sat this round out
+    pub excluded: Value,
+    /// The players that lost this round and sit out the next one
+    pub eliminated: Value,
+    pub winner: Option<String>,
+    pub played_at: DateTime<Utc>,
+    /// Maps the players to their commitment and the nonce it was verified with, empty
+    /// unless the game used commit-reveal
+    pub commitments: Value,
+}
+
+impl GameRound {
+    pub fn find_by_game(conn: &PgConnection, game_id: &str) -> Result<Vec<GameRound>, GlobalError> {
+        game_rounds::table
+            .filter(game_rounds::game_id.eq(game_id))
+            .order(game_rounds::round.asc())
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "game_rounds"]
+pub struct NewGameRound<'a> {
+    pub game_id: &'a str,
+    pub round: i32,
+    pub choices: Value,
+    pub excluded: Value,
+    pub eliminated: Value,
+    pub winner: Option<&'a str>,
+    pub commitments: Value,
+}
+
+impl<'a> NewGameRound<'a> {
+    pub fn store(&self, conn: &PgConnection) -> Result<usize, GlobalError> {
+        diesel::insert_into(game_rounds::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
@@ -1,9 +1,18 @@
 use super::error::GlobalError;
 use crate::schema::hall_of_fame;
-use diesel::{ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use crate::services::rating::DEFAULT_RATING;
+use diesel::{
+    sql_types::{BigInt, Double, Integer, Varchar},
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+};
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+/// The games the user won, 0 if they aren't rated yet
+const WINS: &str = "COALESCE(ratings.wins, 0)";
+/// The games the user played, 0 if they aren't rated yet
+const GAMES_PLAYED: &str = "COALESCE(ratings.games_played, 0)";
+
 #[derive(Queryable, Debug, Clone, PartialEq, Serialize, Deserialize, AsChangeset)]
 #[table_name = "hall_of_fame"]
 pub struct HallOfFameEntry {
@@ -24,18 +33,143 @@ impl HallOfFameEntry {
             .map_err(|e| GlobalError::DieselError(e))
     }
 
-    pub fn find_all(conn: &PgConnection) -> Result<Vec<HallOfFameEntry>, GlobalError> {
-        hall_of_fame::table
-            .order(hall_of_fame::score.desc())
+    pub fn incr_score(&mut self) {
+        self.score += 1;
+    }
+
+    /// Returns a page of the hall of fame ranked by the given field, best first
+    pub fn standings(
+        conn: &PgConnection,
+        sort: HofSort,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Standing>, GlobalError> {
+        let query = format!(
+            "{} SELECT * FROM standings ORDER BY rank LIMIT $1 OFFSET $2",
+            Self::ranked(sort)
+        );
+        diesel::sql_query(query)
+            .bind::<BigInt, _>(limit)
+            .bind::<BigInt, _>(offset)
             .load(conn)
-            .map_err(|e| GlobalError::DieselError(e))
+            .map_err(GlobalError::DieselError)
     }
 
-    pub fn incr_score(&mut self) {
-        self.score += 1;
+    /// Returns the user's standing along with up to `neighbours` players ranked right above
+    /// and below them. Empty if the user isn't in the hall of fame.
+    pub fn standings_around(
+        conn: &PgConnection,
+        sort: HofSort,
+        user_id: &str,
+        neighbours: i64,
+    ) -> Result<Vec<Standing>, GlobalError> {
+        let query = format!(
+            "{} , me AS (SELECT rank FROM standings WHERE user_id = $1) \
+            SELECT standings.* FROM standings, me \
+            WHERE standings.rank BETWEEN me.rank - $2 AND me.rank + $2 \
+            ORDER BY standings.rank",
+            Self::ranked(sort)
+        );
+        diesel::sql_query(query)
+            .bind::<Varchar, _>(user_id)
+            .bind::<BigInt, _>(neighbours)
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Returns the `standings` CTE ranking every hall of fame entry with the username and
+    /// rating of its user
+    fn ranked(sort: HofSort) -> String {
+        format!(
+            "WITH standings AS ( \
+                SELECT ROW_NUMBER() OVER (ORDER BY {order}) AS rank, hall_of_fame.user_id, \
+                users.username, hall_of_fame.score, {rating} AS rating, {wins} AS wins, \
+                {games_played} AS games_played, {win_rate} AS win_rate \
+                FROM hall_of_fame INNER JOIN users ON users.id = hall_of_fame.user_id \
+                LEFT JOIN ratings ON ratings.user_id = hall_of_fame.user_id \
+            )",
+            order = sort.order(),
+            rating = HofSort::Rating.column(),
+            wins = WINS,
+            games_played = GAMES_PLAYED,
+            win_rate = HofSort::WinRate.column(),
+        )
     }
 }
 
+/// What the hall of fame can be sorted by.
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HofSort {
+    /// The hall of fame score
+    #[default]
+    Score,
+    Rating,
+    Wins,
+    WinRate,
+    GamesPlayed,
+}
+
+impl HofSort {
+    /// Returns the SQL expression of the field, players without a rating count as unrated
+    /// newcomers
+    fn column(&self) -> String {
+        match self {
+            Self::Score => "hall_of_fame.score".to_string(),
+            Self::Rating => format!("COALESCE(ratings.rating, {:?})", DEFAULT_RATING),
+            Self::Wins => WINS.to_string(),
+            Self::WinRate => format!("{}::float8 / GREATEST({}, 1)", WINS, GAMES_PLAYED),
+            Self::GamesPlayed => GAMES_PLAYED.to_string(),
+        }
+    }
+
+    /// Returns the `ORDER BY` clause ranking the players, ties are broken by user ID so the
+    /// ranks stay stable between pages
+    fn order(&self) -> String {
+        format!("{} DESC, hall_of_fame.user_id", self.column())
+    }
+}
+
+/// Centers the hall of fame page on a player instead of using the offset.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum HofAround {
+    /// The logged in user
+    Me,
+}
+
+/// Query parameters of the hall of fame endpoint.
+#[derive(Debug, Deserialize)]
+pub struct HofQuery {
+    #[serde(default)]
+    pub sort: HofSort,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub around: Option<HofAround>,
+}
+
+/// A single line of the hall of fame.
+#[derive(QueryableByName, Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+    /// The 1-based position of the player with the requested sort
+    #[sql_type = "BigInt"]
+    pub rank: i64,
+    #[sql_type = "Varchar"]
+    pub user_id: String,
+    #[sql_type = "Varchar"]
+    pub username: String,
+    #[sql_type = "Integer"]
+    pub score: i32,
+    #[sql_type = "Double"]
+    pub rating: f64,
+    #[sql_type = "Integer"]
+    pub wins: i32,
+    #[sql_type = "Integer"]
+    pub games_played: i32,
+    #[sql_type = "Double"]
+    pub win_rate: f64,
+}
+
 #[derive(Insertable, Debug)]
 #[table_name = "hall_of_fame"]
 pub struct NewHoFEntry<'a> {
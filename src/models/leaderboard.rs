@@ -0,0 +1,93 @@
+use super::error::GlobalError;
+use super::game::GAME_FINISHED;
+use chrono::{DateTime, Utc};
+use diesel::{
+    sql_types::{BigInt, Integer, Timestamptz, Varchar},
+    PgConnection, RunQueryDsl,
+};
+use serde::{Deserialize, Serialize};
+
+/// The time windows leaderboards can be scoped to.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardWindow {
+    /// Since midnight UTC
+    Today,
+    /// Since monday midnight UTC
+    Week,
+    /// Since the current season started
+    Season,
+}
+
+/// Query parameters of the leaderboard endpoints.
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    pub limit: Option<i64>,
+}
+
+/// A player's position on a leaderboard, computed from finished games.
+#[derive(QueryableByName, Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    #[sql_type = "BigInt"]
+    pub rank: i64,
+    #[sql_type = "Varchar"]
+    pub user_id: String,
+    #[sql_type = "Varchar"]
+    pub username: String,
+    #[sql_type = "BigInt"]
+    pub wins: i64,
+    #[sql_type = "BigInt"]
+    pub games_played: i64,
+}
+
+impl LeaderboardEntry {
+    /// Ranks the players by the games they won, then by the games they played, among the
+    /// games that finished in the given window
+    pub fn between(
+        conn: &PgConnection,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>, GlobalError> {
+        diesel::sql_query(Self::ranked("games.ended_at >= $2 AND games.ended_at < $3"))
+            .bind::<BigInt, _>(limit)
+            .bind::<Timestamptz, _>(from)
+            .bind::<Timestamptz, _>(to)
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Ranks the players by the games they won, then by the games they played, among the
+    /// finished games of the season
+    pub fn season(
+        conn: &PgConnection,
+        season_id: i32,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>, GlobalError> {
+        diesel::sql_query(Self::ranked("games.season_id = $2"))
+            .bind::<BigInt, _>(limit)
+            .bind::<Integer, _>(season_id)
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Returns the ranking query for the finished games matching the filter, `$1` is the limit
+    fn ranked(filter: &str) -> String {
+        format!(
+            "SELECT ROW_NUMBER() OVER (ORDER BY \
+                COUNT(*) FILTER (WHERE games.winner = game_players.user_id) DESC, \
+                COUNT(*) DESC, game_players.user_id) AS rank, \
+            game_players.user_id, users.username, \
+            COUNT(*) FILTER (WHERE games.winner = game_players.user_id) AS wins, \
+            COUNT(*) AS games_played \
+            FROM game_players \
+            INNER JOIN games ON games.id = game_players.game_id \
+            INNER JOIN users ON users.id = game_players.user_id \
+            WHERE games.status = '{status}' AND {filter} \
+            GROUP BY game_players.user_id, users.username \
+            ORDER BY rank LIMIT $1",
+            status = GAME_FINISHED,
+            filter = filter
+        )
+    }
+}
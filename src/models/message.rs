@@ -1,7 +1,10 @@
-use crate::actors::chat::models::messages::ChatMessage;
+use super::error::GlobalError;
+use super::message_reaction::MessageReaction;
+use super::message_revision::NewMessageRevision;
+use crate::actors::chat::models::messages::{ChatMessage, MessagePage};
 use crate::schema::messages;
-use chrono::{DateTime, Local};
-use diesel::{prelude::*, Insertable, Queryable, RunQueryDsl};
+use chrono::{DateTime, Utc};
+use diesel::{dsl::sql, prelude::*, sql_types::BigInt, Insertable, Queryable, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 
 #[derive(Queryable, PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -10,9 +13,247 @@ pub struct Message {
     sender_id: String,
     receiver_user: Option<String>,
     receiver_room: Option<String>,
-    content: String,
-    timestamp: DateTime<Local>,
+    content: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    read: Option<bool>,
+    edited_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+    deleted_by: Option<String>,
+    reply_to: Option<String>,
 }
+
+/// The number of unread private messages from a single sender.
+#[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadCount {
+    pub sender_id: String,
+    pub count: i64,
+}
+
+/// Query parameters of the paginated history endpoints.
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// The ID of the oldest message the client already has
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// The parties whose messages are being looked up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Conversation {
+    /// All messages sent to the room with the given ID
+    Room(String),
+    /// All messages exchanged between the two given user IDs
+    Direct(String, String),
+    /// All replies to the message with the given ID
+    Thread(String),
+}
+
+impl Message {
+    /// Returns up to `limit` messages of the conversation sent before the message with the
+    /// ID `before`, oldest first. If `before` is omitted the latest messages are returned.
+    pub fn find_page(
+        conn: &PgConnection,
+        conversation: &Conversation,
+        before: Option<&str>,
+        limit: i64,
+    ) -> Result<MessagePage, GlobalError> {
+        let mut query = match conversation {
+            Conversation::Room(room_id) => messages::table
+                .filter(messages::receiver_room.eq(room_id))
+                .into_boxed(),
+            Conversation::Direct(id, other_id) => messages::table
+                .filter(
+                    messages::sender_id
+                        .eq(id)
+                        .and(messages::receiver_user.eq(other_id))
+                        .or(messages::sender_id
+                            .eq(other_id)
+                            .and(messages::receiver_user.eq(id))),
+                )
+                .into_boxed(),
+            Conversation::Thread(message_id) => messages::table
+                .filter(messages::reply_to.eq(message_id))
+                .into_boxed(),
+        };
+        if let Some(before) = before {
+            let timestamp = messages::table
+                .find(before)
+                .select(messages::timestamp)
+                .first::<Option<DateTime<Utc>>>(conn)
+                .optional()
+                .map_err(GlobalError::DieselError)?
+                .flatten();
+            let timestamp = match timestamp {
+                Some(timestamp) => timestamp,
+                None => return Ok(MessagePage::default()),
+            };
+            query = query.filter(
+                messages::timestamp.lt(timestamp).or(messages::timestamp
+                    .eq(timestamp)
+                    .and(messages::id.lt(before))),
+            );
+        }
+        // Fetch one extra to know whether there's anything left after this page
+        let mut messages = query
+            .order((messages::timestamp.desc(), messages::id.desc()))
+            .limit(limit + 1)
+            .load::<Message>(conn)
+            .map_err(GlobalError::DieselError)?;
+        let next = if messages.len() as i64 > limit {
+            messages.truncate(limit as usize);
+            messages.last().map(|message| message.id.clone())
+        } else {
+            None
+        };
+        messages.reverse();
+        let mut messages: Vec<ChatMessage> = messages.into_iter().map(Message::convert).collect();
+        MessageReaction::attach(conn, &mut messages)?;
+        Self::attach_reply_counts(conn, &mut messages)?;
+        Ok(MessagePage { messages, next })
+    }
+
+    /// Returns the latest `limit` messages sent to anyone, oldest first.
+    pub fn find_recent(conn: &PgConnection, limit: i64) -> Result<Vec<Message>, GlobalError> {
+        let mut messages = messages::table
+            .order(messages::timestamp.desc())
+            .limit(limit)
+            .load::<Message>(conn)
+            .map_err(GlobalError::DieselError)?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Fills in how many replies each of the messages got, deleted replies aside
+    pub fn attach_reply_counts(
+        conn: &PgConnection,
+        messages: &mut [ChatMessage],
+    ) -> Result<(), GlobalError> {
+        let message_ids: Vec<String> = messages.iter().map(|m| m.id.clone()).collect();
+        let counts = messages::table
+            .filter(messages::reply_to.eq_any(&message_ids))
+            .filter(messages::deleted_at.is_null())
+            .group_by(messages::reply_to)
+            .select((messages::reply_to, sql::<BigInt>("COUNT(*)")))
+            .load::<(Option<String>, i64)>(conn)
+            .map_err(GlobalError::DieselError)?;
+        for (reply_to, count) in counts {
+            if let Some(message) = messages
+                .iter_mut()
+                .find(|m| Some(&m.id) == reply_to.as_ref())
+            {
+                message.reply_count = count;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn find_by_id(conn: &PgConnection, id: &str) -> Result<Option<Message>, GlobalError> {
+        messages::table
+            .find(id)
+            .first::<Message>(conn)
+            .optional()
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Replaces the content of the message, keeping the previous one as a revision. Fails
+    /// with `NotFound` if the message doesn't exist or was deleted.
+    pub fn edit(
+        conn: &PgConnection,
+        id: &str,
+        previous: &str,
+        content: &str,
+        edited_at: DateTime<Utc>,
+    ) -> Result<(), GlobalError> {
+        conn.transaction::<_, GlobalError, _>(|| {
+            NewMessageRevision::store(conn, id, previous, edited_at)?;
+            let updated = diesel::update(messages::table.find(id))
+                .filter(messages::deleted_at.is_null())
+                .set((
+                    messages::content.eq(content),
+                    messages::edited_at.eq(edited_at),
+                ))
+                .execute(conn)?;
+            if updated == 0 {
+                return Err(GlobalError::NotFound);
+            }
+            Ok(())
+        })
+    }
+
+    /// Marks the message deleted, its content is kept but never sent to clients again
+    pub fn soft_delete(
+        conn: &PgConnection,
+        id: &str,
+        deleted_by: &str,
+    ) -> Result<usize, GlobalError> {
+        diesel::update(messages::table.find(id))
+            .set((
+                messages::deleted_at.eq(Utc::now()),
+                messages::deleted_by.eq(deleted_by),
+            ))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Marks the given private messages received by `receiver_id` as read. Returns the
+    /// messages that weren't read before.
+    pub fn mark_read(
+        conn: &PgConnection,
+        receiver_id: &str,
+        message_ids: &[String],
+    ) -> Result<Vec<Message>, GlobalError> {
+        diesel::update(messages::table)
+            .filter(messages::id.eq_any(message_ids))
+            .filter(messages::receiver_user.eq(receiver_id))
+            .filter(messages::read.eq(false).or(messages::read.is_null()))
+            .set(messages::read.eq(true))
+            .get_results(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Counts the unread private messages `receiver_id` got from each sender.
+    pub fn count_unread(
+        conn: &PgConnection,
+        receiver_id: &str,
+    ) -> Result<Vec<UnreadCount>, GlobalError> {
+        messages::table
+            .filter(messages::receiver_user.eq(receiver_id))
+            .filter(messages::read.eq(false))
+            .filter(messages::deleted_at.is_null())
+            .group_by(messages::sender_id)
+            .select((messages::sender_id, sql::<BigInt>("COUNT(*)")))
+            .load::<UnreadCount>(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Converts a message from the database to the message used by the chat server. Deleted
+    /// messages lose their content.
+    pub fn convert(self) -> ChatMessage {
+        let deleted = self.deleted_at.is_some();
+        ChatMessage {
+            id: self.id,
+            sender_id: self.sender_id,
+            receiver_id: self
+                .receiver_room
+                .or(self.receiver_user)
+                .unwrap_or_default(),
+            content: if deleted {
+                String::new()
+            } else {
+                self.content.unwrap_or_default()
+            },
+            read: self.read.unwrap_or(false),
+            timestamp: self.timestamp,
+            edited_at: self.edited_at,
+            deleted,
+            reactions: vec![],
+            reply_to: self.reply_to,
+            reply_count: 0,
+            client_ref: None,
+        }
+    }
+}
+
 #[derive(Insertable, Debug)]
 #[table_name = "messages"]
 pub struct NewMessage<'a> {
@@ -21,7 +262,9 @@ pub struct NewMessage<'a> {
     receiver_user: Option<&'a str>,
     receiver_room: Option<&'a str>,
     content: &'a str,
-    timestamp: DateTime<Local>,
+    timestamp: DateTime<Utc>,
+    read: Option<bool>,
+    reply_to: Option<&'a str>,
 }
 
 impl<'a> NewMessage<'a> {
@@ -44,7 +287,10 @@ impl<'a> NewMessage<'a> {
                 None
             },
             content: &message.content,
-            timestamp: Local::now(),
+            timestamp: message.timestamp.unwrap_or_else(Utc::now),
+            // Read receipts are only kept for private messages
+            read: if room { None } else { Some(false) },
+            reply_to: message.reply_to.as_deref(),
         };
         diesel::insert_into(messages::table)
             .values(new_message)
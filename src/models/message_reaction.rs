@@ -0,0 +1,80 @@
+use super::error::GlobalError;
+use crate::actors::chat::models::messages::{ChatMessage, ReactionCount};
+use crate::schema::message_reactions;
+use diesel::{dsl::sql, prelude::*, sql_types::BigInt, Insertable, RunQueryDsl};
+
+/// A user's reaction to a chat message, a user can react with several emojis.
+#[derive(Insertable, Debug)]
+#[table_name = "message_reactions"]
+pub struct MessageReaction<'a> {
+    message_id: &'a str,
+    user_id: &'a str,
+    emoji: &'a str,
+}
+
+impl<'a> MessageReaction<'a> {
+    /// Returns the amount of reactions added, 0 if the user already reacted with the emoji
+    pub fn add(
+        conn: &PgConnection,
+        message_id: &'a str,
+        user_id: &'a str,
+        emoji: &'a str,
+    ) -> Result<usize, GlobalError> {
+        diesel::insert_into(message_reactions::table)
+            .values(Self {
+                message_id,
+                user_id,
+                emoji,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn remove(
+        conn: &PgConnection,
+        message_id: &str,
+        user_id: &str,
+        emoji: &str,
+    ) -> Result<usize, GlobalError> {
+        diesel::delete(message_reactions::table.find((message_id, user_id, emoji)))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Counts the reactions of each message per emoji, most used first
+    pub fn count(
+        conn: &PgConnection,
+        message_ids: &[&str],
+    ) -> Result<Vec<(String, String, i64)>, GlobalError> {
+        message_reactions::table
+            .filter(message_reactions::message_id.eq_any(message_ids))
+            .group_by((message_reactions::message_id, message_reactions::emoji))
+            .select((
+                message_reactions::message_id,
+                message_reactions::emoji,
+                sql::<BigInt>("COUNT(*)"),
+            ))
+            .order((sql::<BigInt>("COUNT(*)").desc(), message_reactions::emoji))
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Fills in the reaction counts of the messages
+    pub fn attach(conn: &PgConnection, messages: &mut [ChatMessage]) -> Result<(), GlobalError> {
+        let message_ids: Vec<String> = messages.iter().map(|m| m.id.clone()).collect();
+        let message_ids: Vec<&str> = message_ids.iter().map(String::as_str).collect();
+        let counts = Self::count(conn, &message_ids)?;
+        for message in messages.iter_mut() {
+            message.reactions = counts
+                .iter()
+                .filter(|(message_id, _, _)| *message_id == message.id)
+                .map(|(_, emoji, count)| ReactionCount {
+                    emoji: emoji.clone(),
+                    count: *count,
+                })
+                .collect();
+        }
+        Ok(())
+    }
+}
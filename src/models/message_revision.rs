@@ -0,0 +1,55 @@
+use super::error::GlobalError;
+use crate::schema::message_revisions;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, Insertable, Queryable, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+/// The content a chat message had before one of its edits.
+#[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
+pub struct MessageRevision {
+    pub id: i32,
+    pub message_id: String,
+    pub content: String,
+    /// When the content got replaced
+    pub edited_at: DateTime<Utc>,
+}
+
+impl MessageRevision {
+    /// Returns the previous contents of the message, oldest first
+    pub fn find_by_message(
+        conn: &PgConnection,
+        message_id: &str,
+    ) -> Result<Vec<MessageRevision>, GlobalError> {
+        message_revisions::table
+            .filter(message_revisions::message_id.eq(message_id))
+            .order(message_revisions::edited_at.asc())
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "message_revisions"]
+pub struct NewMessageRevision<'a> {
+    message_id: &'a str,
+    content: &'a str,
+    edited_at: DateTime<Utc>,
+}
+
+impl<'a> NewMessageRevision<'a> {
+    pub fn store(
+        conn: &PgConnection,
+        message_id: &'a str,
+        content: &'a str,
+        edited_at: DateTime<Utc>,
+    ) -> Result<usize, GlobalError> {
+        diesel::insert_into(message_revisions::table)
+            .values(Self {
+                message_id,
+                content,
+                edited_at,
+            })
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
@@ -4,4 +4,16 @@ pub mod message;
 pub mod error;
 pub mod authentication;
 pub mod room_connection;
-pub mod hall_of_fame;
\ No newline at end of file
+pub mod room_ban;
+pub mod hall_of_fame;
+pub mod game;
+pub mod game_player;
+pub mod game_round;
+pub mod rating;
+pub mod rating_history;
+pub mod season;
+pub mod season_standing;
+pub mod leaderboard;
+pub mod tournament;
+pub mod message_revision;
+pub mod message_reaction;
\ No newline at end of file
@@ -0,0 +1,46 @@
+use super::error::GlobalError;
+use crate::schema::ratings;
+use crate::services::rating::DEFAULT_RATING;
+use diesel::{prelude::*, Insertable, Queryable, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Insertable, AsChangeset, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[table_name = "ratings"]
+pub struct Rating {
+    pub user_id: String,
+    pub rating: f64,
+    pub wins: i32,
+    pub games_played: i32,
+}
+
+impl Rating {
+    /// Returns a fresh rating for a user that hasn't played yet
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            rating: DEFAULT_RATING,
+            wins: 0,
+            games_played: 0,
+        }
+    }
+
+    pub fn find_by_users(
+        conn: &PgConnection,
+        user_ids: &[&str],
+    ) -> Result<Vec<Rating>, GlobalError> {
+        ratings::table
+            .filter(ratings::user_id.eq_any(user_ids))
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn upsert(&self, conn: &PgConnection) -> Result<usize, GlobalError> {
+        diesel::insert_into(ratings::table)
+            .values(self)
+            .on_conflict(ratings::user_id)
+            .do_update()
+            .set(self)
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
@@ -0,0 +1,37 @@
+use crate::schema::rating_history;
+use chrono::{DateTime, Utc};
+use diesel::{PgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use super::error::GlobalError;
+/// How a game changed the rating of a player.
+#[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
+pub struct RatingChange {
+    pub id: i32,
+    pub user_id: String,
+    pub game_id: String,
+    pub rating_before: f64,
+    pub rating_after: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "rating_history"]
+pub struct NewRatingChange<'a> {
+    pub user_id: &'a str,
+    pub game_id: &'a str,
+    pub rating_before: f64,
+    pub rating_after: f64,
+}
+
+impl<'a> NewRatingChange<'a> {
+    pub fn store_all(
+        conn: &PgConnection,
+        changes: &[NewRatingChange<'a>],
+    ) -> Result<usize, GlobalError> {
+        diesel::insert_into(rating_history::table)
+            .values(changes)
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
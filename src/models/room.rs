@@ -1,5 +1,8 @@
 use crate::{actors::chat::models::room::PublicRoom, schema::rooms};
-use diesel::{Insertable, PgConnection, Queryable, RunQueryDsl};
+use diesel::{
+    ExpressionMethods, Insertable, OptionalExtension, PgConnection, QueryDsl, Queryable,
+    RunQueryDsl,
+};
 use serde::{Deserialize, Serialize};
 
 use super::error::GlobalError;
@@ -9,7 +12,73 @@ pub struct Room {
     id: String,
     name: String,
     password: Option<String>,
-    admin: String,
+    admin: Option<String>,
+    archived: bool,
+}
+
+impl Room {
+    /// Returns all rooms that aren't archived
+    pub fn find_active(conn: &PgConnection) -> Result<Vec<Room>, GlobalError> {
+        rooms::table
+            .filter(rooms::archived.eq(false))
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn find_by_id(conn: &PgConnection, id: &str) -> Result<Option<Room>, GlobalError> {
+        rooms::table
+            .find(id)
+            .first::<Room>(conn)
+            .optional()
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn rename(conn: &PgConnection, id: &str, name: &str) -> Result<usize, GlobalError> {
+        diesel::update(rooms::table.find(id))
+            .set(rooms::name.eq(name))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn set_admin(conn: &PgConnection, id: &str, admin: &str) -> Result<usize, GlobalError> {
+        diesel::update(rooms::table.find(id))
+            .set(rooms::admin.eq(admin))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Archived rooms keep their messages but are no longer loaded by the chat server
+    pub fn archive(conn: &PgConnection, id: &str) -> Result<usize, GlobalError> {
+        diesel::update(rooms::table.find(id))
+            .set(rooms::archived.eq(true))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Deletes the room along with its messages, connections and bans
+    pub fn delete(conn: &PgConnection, id: &str) -> Result<usize, GlobalError> {
+        diesel::delete(rooms::table.find(id))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// Converts a room from the database to an empty room used by the chat server
+    pub fn convert(self) -> PublicRoom {
+        PublicRoom {
+            id: self.id,
+            name: self.name,
+            users: Default::default(),
+            messages: vec![],
+            protected: self.password.is_some(),
+            password: self.password,
+            admin: self.admin,
+            banned: Default::default(),
+        }
+    }
 }
 
 #[derive(Insertable, Debug)]
@@ -30,7 +99,7 @@ impl<'a> NewRoom<'a> {
         let new_room = Self {
             id: &room.id,
             name: &room.name,
-            password: Some("To do"),
+            password: room.password.as_deref(),
             admin,
         };
         diesel::insert_into(rooms::table)
@@ -0,0 +1,48 @@
+use crate::schema::room_bans;
+use diesel::{OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use super::error::GlobalError;
+#[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
+pub struct RoomBan {
+    pub room_id: String,
+    pub user_id: String,
+}
+
+impl RoomBan {
+    pub fn exists(conn: &PgConnection, room_id: &str, user_id: &str) -> Result<bool, GlobalError> {
+        room_bans::table
+            .find((room_id, user_id))
+            .first::<RoomBan>(conn)
+            .optional()
+            .map(|ban| ban.is_some())
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn find_all(conn: &PgConnection) -> Result<Vec<RoomBan>, GlobalError> {
+        room_bans::table
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "room_bans"]
+pub struct NewRoomBan<'a> {
+    room_id: &'a str,
+    user_id: &'a str,
+}
+
+impl<'a> NewRoomBan<'a> {
+    pub fn store(
+        conn: &PgConnection,
+        room_id: &'a str,
+        user_id: &'a str,
+    ) -> Result<usize, GlobalError> {
+        diesel::insert_into(room_bans::table)
+            .values(Self { room_id, user_id })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+}
@@ -1,12 +1,35 @@
 use crate::schema::room_connections;
-use diesel::{PgConnection, RunQueryDsl};
+use diesel::{OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 
 use super::error::GlobalError;
 #[derive(Queryable, Debug, Clone, Serialize, Deserialize)]
 pub struct RoomConnection {
-    room_id: String,
-    user_id: String,
+    pub room_id: String,
+    pub user_id: String,
+}
+
+impl RoomConnection {
+    pub fn exists(conn: &PgConnection, room_id: &str, user_id: &str) -> Result<bool, GlobalError> {
+        room_connections::table
+            .find((room_id, user_id))
+            .first::<RoomConnection>(conn)
+            .optional()
+            .map(|connection| connection.is_some())
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn remove(conn: &PgConnection, room_id: &str, user_id: &str) -> Result<usize, GlobalError> {
+        diesel::delete(room_connections::table.find((room_id, user_id)))
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn find_all(conn: &PgConnection) -> Result<Vec<RoomConnection>, GlobalError> {
+        room_connections::table
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
 }
 #[derive(Insertable, Debug, AsChangeset)]
 #[table_name = "room_connections"]
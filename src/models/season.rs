@@ -0,0 +1,75 @@
+use super::error::GlobalError;
+use super::leaderboard::LeaderboardEntry;
+use super::season_standing::SeasonStanding;
+use crate::schema::seasons;
+use chrono::{DateTime, Utc};
+use diesel::{prelude::*, Insertable, Queryable, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Season {
+    pub id: i32,
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    /// Only set once the season is over
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// Body of the season rollover endpoint.
+#[derive(Debug, Deserialize)]
+pub struct RolloverForm {
+    /// The name of the season that starts
+    pub name: String,
+}
+
+impl Season {
+    /// Returns the season that's currently running
+    pub fn find_current(conn: &PgConnection) -> Result<Option<Season>, GlobalError> {
+        seasons::table
+            .filter(seasons::ends_at.is_null())
+            .order(seasons::starts_at.desc())
+            .first::<Season>(conn)
+            .optional()
+            .map_err(GlobalError::DieselError)
+    }
+
+    pub fn find_by_id(conn: &PgConnection, id: i32) -> Result<Option<Season>, GlobalError> {
+        seasons::table
+            .find(id)
+            .first::<Season>(conn)
+            .optional()
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Returns all seasons, latest first
+    pub fn find_all(conn: &PgConnection) -> Result<Vec<Season>, GlobalError> {
+        seasons::table
+            .order(seasons::starts_at.desc())
+            .load(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Ends the current season, archives its final standings and starts a new one with the
+    /// given name
+    pub fn rollover(conn: &PgConnection, name: &str) -> Result<Season, GlobalError> {
+        conn.transaction::<_, GlobalError, _>(|| {
+            if let Some(current) = Self::find_current(conn)? {
+                diesel::update(seasons::table.find(current.id))
+                    .set(seasons::ends_at.eq(Utc::now()))
+                    .execute(conn)?;
+                let standings = LeaderboardEntry::season(conn, current.id, i64::MAX)?;
+                SeasonStanding::archive(conn, current.id, &standings)?;
+            }
+            diesel::insert_into(seasons::table)
+                .values(NewSeason { name })
+                .get_result::<Season>(conn)
+                .map_err(GlobalError::DieselError)
+        })
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "seasons"]
+pub struct NewSeason<'a> {
+    name: &'a str,
+}
@@ -0,0 +1,75 @@
+use super::error::GlobalError;
+use super::leaderboard::LeaderboardEntry;
+use crate::schema::{season_standings, users};
+use diesel::{prelude::*, Insertable, RunQueryDsl};
+
+/// The final standing of a player in a season that's over.
+#[derive(Insertable, Debug)]
+#[table_name = "season_standings"]
+pub struct SeasonStanding<'a> {
+    season_id: i32,
+    user_id: &'a str,
+    rank: i32,
+    wins: i32,
+    games_played: i32,
+}
+
+impl<'a> SeasonStanding<'a> {
+    /// Archives the leaderboard as the final standings of the season
+    pub fn archive(
+        conn: &PgConnection,
+        season_id: i32,
+        entries: &'a [LeaderboardEntry],
+    ) -> Result<usize, GlobalError> {
+        let standings: Vec<SeasonStanding> = entries
+            .iter()
+            .map(|entry| SeasonStanding {
+                season_id,
+                user_id: &entry.user_id,
+                rank: entry.rank as i32,
+                wins: entry.wins as i32,
+                games_played: entry.games_played as i32,
+            })
+            .collect();
+        diesel::insert_into(season_standings::table)
+            .values(&standings)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map_err(GlobalError::DieselError)
+    }
+
+    /// Returns the archived standings of the season, best first
+    pub fn find_by_season(
+        conn: &PgConnection,
+        season_id: i32,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>, GlobalError> {
+        season_standings::table
+            .inner_join(users::table)
+            .filter(season_standings::season_id.eq(season_id))
+            .select((
+                season_standings::rank,
+                season_standings::user_id,
+                users::username,
+                season_standings::wins,
+                season_standings::games_played,
+            ))
+            .order(season_standings::rank.asc())
+            .limit(limit)
+            .load::<(i32, String, String, i32, i32)>(conn)
+            .map(|rows| {
+                rows.into_iter()
+                    .map(
+                        |(rank, user_id, username, wins, games_played)| LeaderboardEntry {
+                            rank: rank as i64,
+                            user_id,
+                            username,
+                            wins: wins as i64,
+                            games_played: games_played as i64,
+                        },
+                    )
+                    .collect()
+            })
+            .map_err(GlobalError::DieselError)
+    }
+}
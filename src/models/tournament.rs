@@ -0,0 +1,98 @@
+use super::error::GlobalError;
+use crate::actors::rps::tournament::{Tournament, TournamentStatus};
+use crate::schema::{tournament_players, tournaments};
+use chrono::Utc;
+use diesel::{pg::upsert::excluded, prelude::*, Insertable, RunQueryDsl};
+use serde_json::Value;
+
+#[derive(Insertable, Debug)]
+#[table_name = "tournaments"]
+pub struct NewTournament<'a> {
+    id: &'a str,
+    name: &'a str,
+    host: &'a str,
+    format: String,
+    best_of: i32,
+    status: String,
+    champion: Option<&'a str>,
+    /// Snapshot of the whole tournament, matches included
+    bracket: Value,
+    ended_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "tournament_players"]
+pub struct NewTournamentPlayer<'a> {
+    tournament_id: &'a str,
+    user_id: &'a str,
+    seed: i32,
+    wins: i32,
+    losses: i32,
+}
+
+/// Loads the tournaments that are still taking registrations or being played from their
+/// bracket snapshots, oldest first
+pub fn find_unfinished(conn: &PgConnection) -> Result<Vec<Tournament>, GlobalError> {
+    let brackets: Vec<Value> = tournaments::table
+        .filter(tournaments::status.ne(format!("{:?}", TournamentStatus::Finished)))
+        .order(tournaments::created_at.asc())
+        .select(tournaments::bracket)
+        .load(conn)?;
+    brackets
+        .into_iter()
+        .map(|bracket| Ok(serde_json::from_value(bracket)?))
+        .collect()
+}
+
+impl<'a> NewTournament<'a> {
+    /// Stores the tournament along with its players, replacing the previous state
+    pub fn store(conn: &PgConnection, tournament: &'a Tournament) -> Result<(), GlobalError> {
+        let finished = tournament.status == TournamentStatus::Finished;
+        let bracket = serde_json::to_value(tournament)?;
+        conn.transaction::<_, GlobalError, _>(|| {
+            diesel::insert_into(tournaments::table)
+                .values(Self {
+                    id: &tournament.id,
+                    name: &tournament.name,
+                    host: &tournament.host,
+                    format: format!("{:?}", tournament.format),
+                    best_of: tournament.best_of as i32,
+                    status: format!("{:?}", tournament.status),
+                    champion: tournament.champion.as_deref(),
+                    bracket,
+                    ended_at: finished.then(Utc::now),
+                })
+                .on_conflict(tournaments::id)
+                .do_update()
+                .set((
+                    tournaments::status.eq(excluded(tournaments::status)),
+                    tournaments::champion.eq(excluded(tournaments::champion)),
+                    tournaments::bracket.eq(excluded(tournaments::bracket)),
+                    tournaments::ended_at.eq(excluded(tournaments::ended_at)),
+                ))
+                .execute(conn)?;
+
+            // Registrations can be withdrawn before the tournament starts
+            diesel::delete(
+                tournament_players::table
+                    .filter(tournament_players::tournament_id.eq(&tournament.id)),
+            )
+            .execute(conn)?;
+            let players: Vec<NewTournamentPlayer> = tournament
+                .entrants
+                .iter()
+                .map(|entrant| NewTournamentPlayer {
+                    tournament_id: &tournament.id,
+                    user_id: &entrant.user_id,
+                    seed: entrant.seed as i32,
+                    wins: entrant.wins as i32,
+                    losses: entrant.losses as i32,
+                })
+                .collect();
+            diesel::insert_into(tournament_players::table)
+                .values(&players)
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+}
@@ -13,6 +13,8 @@ pub struct User {
     pub username: String,
     #[serde(skip_serializing)]
     pub password: String,
+    /// Only granted by hand in the database, see the `admins` migration
+    pub is_admin: bool,
 }
 
 #[derive(Insertable, Debug)]
@@ -18,6 +18,7 @@ pub async fn handler(
         ws::WsResponseBuilder::new(
             WsChatSession {
                 id: chat_user.id.clone(),
+                connection_id: uuid::Uuid::new_v4().to_string(),
                 username: chat_user.username,
                 room: chat_user.id,
                 heartbeat: Instant::now(),
@@ -0,0 +1,2 @@
+pub mod replay;
+pub mod user;
\ No newline at end of file
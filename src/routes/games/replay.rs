@@ -0,0 +1,16 @@
+use crate::models::error::GlobalError;
+use crate::models::game::{Game, GameReplay};
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns the game along with every round played in it, in order.
+pub async fn handler(
+    game_id: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<Json<GameReplay>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    match Game::find_replay(&db_connection, &game_id)? {
+        Some(replay) => Ok(Json(replay)),
+        None => Err(GlobalError::NotFound),
+    }
+}
@@ -0,0 +1,14 @@
+use crate::models::error::GlobalError;
+use crate::models::game::{Game, GameRecord};
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns all games the user played in along with their players, latest first.
+pub async fn handler(
+    user_id: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<Json<Vec<GameRecord>>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    let records = Game::find_records(&db_connection, &user_id)?;
+    Ok(Json(records))
+}
@@ -1,15 +1,43 @@
+use crate::actors::chat::models::chat_user::ChatUser;
 use crate::models::error::GlobalError;
-use crate::models::hall_of_fame::HallOfFameEntry;
+use crate::models::hall_of_fame::{HallOfFameEntry, HofAround, HofQuery, Standing};
 use crate::state::app::AppState;
 use actix_web::{web, web::Json};
 
-pub async fn handler(state: web::Data<AppState>) -> Result<Json<Vec<HallOfFameEntry>>, GlobalError> {
+/// Default amount of players on a hall of fame page
+const HOF_PAGE_SIZE: i64 = 25;
+/// Maximum amount of players on a hall of fame page
+const MAX_HOF_PAGE_SIZE: i64 = 100;
+
+/// Returns a page of the hall of fame along with the players' ratings, sorted by
+/// `?sort=score|rating|wins|win_rate|games_played`. Sorts by hall of fame score by default.
+/// With `?around=me` the page is centered on the logged in user instead of starting at `offset`.
+pub async fn handler(
+    query: web::Query<HofQuery>,
+    user: web::ReqData<ChatUser>,
+    state: web::Data<AppState>,
+) -> Result<Json<Vec<Standing>>, GlobalError> {
     let connection = match state.db_pool.get() {
         Ok(conn) => conn,
         Err(_) => return Err(GlobalError::R2D2Error),
     };
-    match HallOfFameEntry::find_all(&connection) {
-        Ok(entries) => Ok(Json(entries)),
+    let limit = query
+        .limit
+        .unwrap_or(HOF_PAGE_SIZE)
+        .clamp(1, MAX_HOF_PAGE_SIZE);
+    let standings = match query.around {
+        Some(HofAround::Me) => {
+            HallOfFameEntry::standings_around(&connection, query.sort, &user.id, limit / 2)
+        }
+        None => HallOfFameEntry::standings(
+            &connection,
+            query.sort,
+            limit,
+            query.offset.unwrap_or(0).max(0),
+        ),
+    };
+    match standings {
+        Ok(standings) => Ok(Json(standings)),
         Err(e) => Err(e),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,28 @@
+use crate::actors::chat::{
+    models::{chat_user::ChatUser, messages::MessagePage},
+    server::{HISTORY_PAGE_SIZE, MAX_PAGE_SIZE},
+};
+use crate::models::error::GlobalError;
+use crate::models::message::{Conversation, HistoryQuery, Message};
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns a page of the private messages the logged in user exchanged with the given user,
+/// oldest first.
+pub async fn handler(
+    user_id: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    user: web::ReqData<ChatUser>,
+    state: web::Data<AppState>,
+) -> Result<Json<MessagePage>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    let conversation = Conversation::Direct(user.id.clone(), user_id.into_inner());
+    let limit = query.limit.unwrap_or(HISTORY_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = Message::find_page(
+        &db_connection,
+        &conversation,
+        query.before.as_deref(),
+        limit,
+    )?;
+    Ok(Json(page))
+}
@@ -0,0 +1,3 @@
+pub mod dm;
+pub mod revisions;
+pub mod room;
\ No newline at end of file
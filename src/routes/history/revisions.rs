@@ -0,0 +1,43 @@
+use crate::actors::chat::models::chat_user::ChatUser;
+use crate::models::error::{AuthenticationError, GlobalError};
+use crate::models::message::Message;
+use crate::models::message_revision::MessageRevision;
+use crate::models::room::Room;
+use crate::models::room_ban::RoomBan;
+use crate::models::room_connection::RoomConnection;
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns the previous contents of the message, oldest first. Only users who can read the
+/// message's conversation get to see them, and deleted messages keep theirs hidden.
+pub async fn handler(
+    message_id: web::Path<String>,
+    user: web::ReqData<ChatUser>,
+    state: web::Data<AppState>,
+) -> Result<Json<Vec<MessageRevision>>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    let message = match Message::find_by_id(&db_connection, &message_id)? {
+        Some(message) => message.convert(),
+        None => return Err(GlobalError::NotFound),
+    };
+    if message.deleted {
+        return Err(GlobalError::NotFound);
+    }
+    match Room::find_by_id(&db_connection, &message.receiver_id)? {
+        Some(room) => {
+            if RoomBan::exists(&db_connection, &message.receiver_id, &user.id)?
+                || (room.is_protected()
+                    && !RoomConnection::exists(&db_connection, &message.receiver_id, &user.id)?)
+            {
+                return Err(AuthenticationError::Forbidden.into());
+            }
+        }
+        None => {
+            if message.sender_id != user.id && message.receiver_id != user.id {
+                return Err(AuthenticationError::Forbidden.into());
+            }
+        }
+    }
+    let revisions = MessageRevision::find_by_message(&db_connection, &message.id)?;
+    Ok(Json(revisions))
+}
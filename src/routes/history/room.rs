@@ -0,0 +1,40 @@
+use crate::actors::chat::{
+    models::{chat_user::ChatUser, messages::MessagePage},
+    server::{HISTORY_PAGE_SIZE, MAX_PAGE_SIZE},
+};
+use crate::models::error::{AuthenticationError, GlobalError};
+use crate::models::message::{Conversation, HistoryQuery, Message};
+use crate::models::room::Room;
+use crate::models::room_ban::RoomBan;
+use crate::models::room_connection::RoomConnection;
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns a page of the room's messages, oldest first. Protected rooms only show their
+/// messages to their members and no room shows them to users banned from it.
+pub async fn handler(
+    room_id: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    user: web::ReqData<ChatUser>,
+    state: web::Data<AppState>,
+) -> Result<Json<MessagePage>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    let room_id = room_id.into_inner();
+    if let Some(room) = Room::find_by_id(&db_connection, &room_id)? {
+        if RoomBan::exists(&db_connection, &room_id, &user.id)? {
+            return Err(AuthenticationError::Forbidden.into());
+        }
+        if room.is_protected() && !RoomConnection::exists(&db_connection, &room_id, &user.id)? {
+            return Err(AuthenticationError::Forbidden.into());
+        }
+    }
+    let conversation = Conversation::Room(room_id);
+    let limit = query.limit.unwrap_or(HISTORY_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = Message::find_page(
+        &db_connection,
+        &conversation,
+        query.before.as_deref(),
+        limit,
+    )?;
+    Ok(Json(page))
+}
@@ -0,0 +1,38 @@
+use crate::models::error::GlobalError;
+use crate::models::leaderboard::{LeaderboardEntry, LeaderboardQuery, LeaderboardWindow};
+use crate::models::season::Season;
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+use chrono::{Datelike, Duration, Utc};
+
+/// Default amount of players on a leaderboard
+pub const LEADERBOARD_SIZE: i64 = 25;
+/// Maximum amount of players on a leaderboard
+pub const MAX_LEADERBOARD_SIZE: i64 = 100;
+
+/// Returns the players ranked by the games they won today, this week or this season.
+pub async fn handler(
+    window: web::Path<LeaderboardWindow>,
+    query: web::Query<LeaderboardQuery>,
+    state: web::Data<AppState>,
+) -> Result<Json<Vec<LeaderboardEntry>>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    let limit = query
+        .limit
+        .unwrap_or(LEADERBOARD_SIZE)
+        .clamp(1, MAX_LEADERBOARD_SIZE);
+    let now = Utc::now();
+    let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let entries = match window.into_inner() {
+        LeaderboardWindow::Today => LeaderboardEntry::between(&db_connection, today, now, limit)?,
+        LeaderboardWindow::Week => {
+            let monday = today - Duration::days(now.weekday().num_days_from_monday() as i64);
+            LeaderboardEntry::between(&db_connection, monday, now, limit)?
+        }
+        LeaderboardWindow::Season => match Season::find_current(&db_connection)? {
+            Some(season) => LeaderboardEntry::season(&db_connection, season.id, limit)?,
+            None => vec![],
+        },
+    };
+    Ok(Json(entries))
+}
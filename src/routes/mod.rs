@@ -1,4 +1,8 @@
 pub mod auth;
 pub mod users;
 pub mod chat;
-pub mod hall_of_fame;
\ No newline at end of file
+pub mod hall_of_fame;
+pub mod history;
+pub mod games;
+pub mod leaderboards;
+pub mod seasons;
\ No newline at end of file
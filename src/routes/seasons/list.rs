@@ -0,0 +1,10 @@
+use crate::models::error::GlobalError;
+use crate::models::season::Season;
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns all seasons, latest first.
+pub async fn handler(state: web::Data<AppState>) -> Result<Json<Vec<Season>>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    Ok(Json(Season::find_all(&db_connection)?))
+}
@@ -0,0 +1,3 @@
+pub mod list;
+pub mod rollover;
+pub mod standings;
\ No newline at end of file
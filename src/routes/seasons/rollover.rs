@@ -0,0 +1,28 @@
+use crate::actors::chat::models::chat_user::ChatUser;
+use crate::actors::rps::models::SetSeason;
+use crate::models::error::{AuthenticationError, GlobalError};
+use crate::models::season::{RolloverForm, Season};
+use crate::models::user::User;
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+use colored::Colorize;
+use tracing::info;
+
+/// Ends the current season, archiving its final standings, and starts a new one. Admins only.
+pub async fn handler(
+    form: web::Json<RolloverForm>,
+    user: web::ReqData<ChatUser>,
+    state: web::Data<AppState>,
+) -> Result<Json<Season>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    match User::find_by_id(&db_connection, &user.id)? {
+        Some(user) if user.is_admin => {}
+        _ => return Err(AuthenticationError::Forbidden.into()),
+    }
+    let season = Season::rollover(&db_connection, &form.name)?;
+    info!("{}{:?}", "SEASON ROLLED OVER : ".purple(), season);
+    state.rps_manager.do_send(SetSeason {
+        season_id: season.id,
+    });
+    Ok(Json(season))
+}
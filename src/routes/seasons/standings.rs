@@ -0,0 +1,29 @@
+use crate::models::error::GlobalError;
+use crate::models::leaderboard::{LeaderboardEntry, LeaderboardQuery};
+use crate::models::season::Season;
+use crate::models::season_standing::SeasonStanding;
+use crate::routes::leaderboards::{LEADERBOARD_SIZE, MAX_LEADERBOARD_SIZE};
+use crate::state::{app::AppState, db_pool};
+use actix_web::{web, web::Json};
+
+/// Returns the final standings of a past season, or the live ones of the current season.
+pub async fn handler(
+    season_id: web::Path<i32>,
+    query: web::Query<LeaderboardQuery>,
+    state: web::Data<AppState>,
+) -> Result<Json<Vec<LeaderboardEntry>>, GlobalError> {
+    let db_connection = db_pool::connect(&state)?;
+    let limit = query
+        .limit
+        .unwrap_or(LEADERBOARD_SIZE)
+        .clamp(1, MAX_LEADERBOARD_SIZE);
+    let season = match Season::find_by_id(&db_connection, *season_id)? {
+        Some(season) => season,
+        None => return Err(GlobalError::NotFound),
+    };
+    let entries = match season.ends_at {
+        Some(_) => SeasonStanding::find_by_season(&db_connection, season.id, limit)?,
+        None => LeaderboardEntry::season(&db_connection, season.id, limit)?,
+    };
+    Ok(Json(entries))
+}
@@ -1,3 +1,40 @@
+table! {
+    game_players (game_id, user_id) {
+        game_id -> Varchar,
+        user_id -> Varchar,
+        score -> Int4,
+        forfeited -> Bool,
+    }
+}
+
+table! {
+    game_rounds (game_id, round) {
+        game_id -> Varchar,
+        round -> Int4,
+        choices -> Jsonb,
+        excluded -> Jsonb,
+        eliminated -> Jsonb,
+        winner -> Nullable<Varchar>,
+        played_at -> Timestamptz,
+        commitments -> Jsonb,
+    }
+}
+
+table! {
+    games (id) {
+        id -> Varchar,
+        name -> Varchar,
+        host -> Varchar,
+        gg_score -> Int4,
+        status -> Varchar,
+        winner -> Nullable<Varchar>,
+        started_at -> Timestamptz,
+        ended_at -> Nullable<Timestamptz>,
+        season_id -> Nullable<Int4>,
+        tournament_id -> Nullable<Varchar>,
+    }
+}
+
 table! {
     hall_of_fame (id) {
         id -> Int4,
@@ -6,6 +43,24 @@ table! {
     }
 }
 
+table! {
+    message_reactions (message_id, user_id, emoji) {
+        message_id -> Varchar,
+        user_id -> Varchar,
+        emoji -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    message_revisions (id) {
+        id -> Int4,
+        message_id -> Varchar,
+        content -> Varchar,
+        edited_at -> Timestamptz,
+    }
+}
+
 table! {
     messages (id) {
         id -> Varchar,
@@ -15,6 +70,37 @@ table! {
         content -> Nullable<Varchar>,
         timestamp -> Nullable<Timestamptz>,
         read -> Nullable<Bool>,
+        edited_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Varchar>,
+        reply_to -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    rating_history (id) {
+        id -> Int4,
+        user_id -> Varchar,
+        game_id -> Varchar,
+        rating_before -> Float8,
+        rating_after -> Float8,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    ratings (user_id) {
+        user_id -> Varchar,
+        rating -> Float8,
+        wins -> Int4,
+        games_played -> Int4,
+    }
+}
+
+table! {
+    room_bans (room_id, user_id) {
+        room_id -> Varchar,
+        user_id -> Varchar,
     }
 }
 
@@ -31,6 +117,51 @@ table! {
         name -> Varchar,
         password -> Nullable<Varchar>,
         admin -> Nullable<Varchar>,
+        archived -> Bool,
+    }
+}
+
+table! {
+    season_standings (season_id, user_id) {
+        season_id -> Int4,
+        user_id -> Varchar,
+        rank -> Int4,
+        wins -> Int4,
+        games_played -> Int4,
+    }
+}
+
+table! {
+    seasons (id) {
+        id -> Int4,
+        name -> Varchar,
+        starts_at -> Timestamptz,
+        ends_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    tournament_players (tournament_id, user_id) {
+        tournament_id -> Varchar,
+        user_id -> Varchar,
+        seed -> Int4,
+        wins -> Int4,
+        losses -> Int4,
+    }
+}
+
+table! {
+    tournaments (id) {
+        id -> Varchar,
+        name -> Varchar,
+        host -> Varchar,
+        format -> Varchar,
+        best_of -> Int4,
+        status -> Varchar,
+        champion -> Nullable<Varchar>,
+        bracket -> Jsonb,
+        created_at -> Timestamptz,
+        ended_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -39,19 +170,50 @@ table! {
         id -> Varchar,
         username -> Varchar,
         password -> Varchar,
+        is_admin -> Bool,
     }
 }
 
+joinable!(game_players -> games (game_id));
+joinable!(game_players -> users (user_id));
+joinable!(game_rounds -> games (game_id));
+joinable!(game_rounds -> users (winner));
+joinable!(games -> seasons (season_id));
+joinable!(games -> tournaments (tournament_id));
 joinable!(hall_of_fame -> users (user_id));
+joinable!(message_reactions -> messages (message_id));
+joinable!(message_reactions -> users (user_id));
+joinable!(message_revisions -> messages (message_id));
 joinable!(messages -> rooms (receiver_room));
+joinable!(rating_history -> games (game_id));
+joinable!(rating_history -> users (user_id));
+joinable!(ratings -> users (user_id));
+joinable!(room_bans -> rooms (room_id));
+joinable!(room_bans -> users (user_id));
 joinable!(room_connections -> rooms (room_id));
 joinable!(room_connections -> users (user_id));
 joinable!(rooms -> users (admin));
+joinable!(season_standings -> seasons (season_id));
+joinable!(season_standings -> users (user_id));
+joinable!(tournament_players -> tournaments (tournament_id));
+joinable!(tournament_players -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    game_players,
+    game_rounds,
+    games,
     hall_of_fame,
+    message_reactions,
+    message_revisions,
     messages,
+    rating_history,
+    ratings,
+    room_bans,
     room_connections,
     rooms,
+    season_standings,
+    seasons,
+    tournament_players,
+    tournaments,
     users,
 );
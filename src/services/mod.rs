@@ -1,2 +1,3 @@
 pub mod jwt;
-pub mod cookie;
\ No newline at end of file
+pub mod cookie;
+pub mod rating;
\ No newline at end of file
@@ -0,0 +1,62 @@
+use std::cmp::Ordering;
+
+/// The rating every player starts with
+pub const DEFAULT_RATING: f64 = 1200.0;
+/// The most a player can gain or lose in a single game
+pub const K_FACTOR: f64 = 32.0;
+
+/// Returns the expected score of a player against an opponent, between 0 and 1
+pub fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+/// Computes the new Elo ratings of the players of a free-for-all game. Each player is given as
+/// their current rating and their standing in the game, the player with the higher standing
+/// beats the other, equal standings are a draw. Every player is rated against every other one,
+/// and the K factor is split between the opponents so a game is worth the same whatever the
+/// number of players.
+pub fn free_for_all(players: &[(f64, i32)]) -> Vec<f64> {
+    if players.len() < 2 {
+        return players.iter().map(|(rating, _)| *rating).collect();
+    }
+    let k = K_FACTOR / (players.len() - 1) as f64;
+    players
+        .iter()
+        .enumerate()
+        .map(|(i, (rating, standing))| {
+            let delta: f64 = players
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (opponent_rating, opponent_standing))| {
+                    let actual = match standing.cmp(opponent_standing) {
+                        Ordering::Greater => 1.0,
+                        Ordering::Equal => 0.5,
+                        Ordering::Less => 0.0,
+                    };
+                    actual - expected_score(*rating, *opponent_rating)
+                })
+                .sum();
+            rating + k * delta
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_for_all_moves_points_from_losers_to_winner() {
+        let ratings = free_for_all(&[(1200.0, 2), (1200.0, 1), (1200.0, 0)]);
+        assert_eq!(ratings[0], 1200.0 + K_FACTOR / 2.0);
+        assert_eq!(ratings[1], 1200.0);
+        assert_eq!(ratings[2], 1200.0 - K_FACTOR / 2.0);
+
+        // Beating a stronger player is worth more than beating a weaker one
+        let upset = free_for_all(&[(1200.0, 1), (1600.0, 0)]);
+        let expected = free_for_all(&[(1600.0, 1), (1200.0, 0)]);
+        assert!(upset[0] - 1200.0 > expected[0] - 1600.0);
+        assert!((upset.iter().sum::<f64>() - 2800.0).abs() < 1e-9);
+    }
+}